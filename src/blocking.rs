@@ -0,0 +1,87 @@
+use bytes::Bytes;
+
+use std::collections::{HashMap, VecDeque};
+
+use tokio::sync::oneshot;
+
+use crate::resp::{RespConcreteType, RespVersion};
+
+/// A client suspended in a blocking command, waiting for one of its keys to be pushed to
+#[derive(Debug)]
+pub struct Waiter {
+    pub protocol: RespVersion,
+    // the command is run again on the client's behalf once a key is ready
+    pub argv: Vec<Bytes>,
+    keys: Vec<Bytes>,
+    reply: oneshot::Sender<RespConcreteType>,
+}
+
+impl Waiter {
+    pub fn new(
+        protocol: RespVersion,
+        argv: Vec<Bytes>,
+        keys: Vec<Bytes>,
+        reply: oneshot::Sender<RespConcreteType>,
+    ) -> Waiter {
+        Waiter {
+            protocol,
+            argv,
+            keys,
+            reply,
+        }
+    }
+
+    /// Whether the client went away, its command must not consume anything anymore
+    pub fn is_gone(&self) -> bool {
+        self.reply.is_closed()
+    }
+
+    pub fn reply(self, reply: RespConcreteType) {
+        // the client may hang up right after the check, there is no one left to tell
+        let _ = self.reply.send(reply);
+    }
+}
+
+/// The clients blocked on each key, in the order they blocked, like redis' `blocking_keys`
+#[derive(Debug, Default)]
+pub struct Blocked {
+    waiters: HashMap<u64, Waiter>,
+    keys: HashMap<Bytes, VecDeque<u64>>,
+}
+
+impl Blocked {
+    pub fn is_empty(&self) -> bool {
+        self.waiters.is_empty()
+    }
+
+    pub fn block(&mut self, client_id: u64, waiter: Waiter) {
+        for key in &waiter.keys {
+            let queue = self.keys.entry(key.clone()).or_default();
+            // a key listed twice is waited on once
+            if !queue.contains(&client_id) {
+                queue.push_back(client_id);
+            }
+        }
+        self.waiters.insert(client_id, waiter);
+    }
+
+    /// The client that blocked first on `key`
+    pub fn first(&self, key: &Bytes) -> Option<(u64, &Waiter)> {
+        let id = *self.keys.get(key)?.front()?;
+        Some((id, &self.waiters[&id]))
+    }
+
+    /// Unblocks a client, taking it off the queues of every key it was waiting on
+    pub fn unblock(&mut self, client_id: u64) -> Option<Waiter> {
+        let waiter = self.waiters.remove(&client_id)?;
+        for key in &waiter.keys {
+            if let Some(queue) = self.keys.get_mut(key) {
+                queue.retain(|&id| id != client_id);
+                if queue.is_empty() {
+                    self.keys.remove(key);
+                }
+            }
+        }
+        Some(waiter)
+    }
+}
@@ -0,0 +1,62 @@
+use std::fmt::Debug;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Time source for everything ttl related. Readings are unix milliseconds, so absolute
+/// timestamps like EXPIREAT's can be stored as is, but they never go backwards
+pub trait Clock: Debug + Send + Sync {
+    fn now_ms(&self) -> i64;
+}
+
+/// Reads the wall clock once at startup and advances with the monotonic clock from then on,
+/// so stepping the system time (e.g. NTP) neither expires nor resurrects keys
+#[derive(Debug)]
+pub struct SystemClock {
+    start: Instant,
+    start_ms: i64,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        let start_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as i64);
+        SystemClock {
+            start: Instant::now(),
+            start_ms,
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> i64 {
+        self.start_ms + self.start.elapsed().as_millis() as i64
+    }
+}
+
+/// A clock that only moves when told to, for stepping through expiry deterministically
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct ManualClock(std::sync::atomic::AtomicI64);
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new(now_ms: i64) -> Self {
+        ManualClock(now_ms.into())
+    }
+
+    pub fn advance(&self, by: std::time::Duration) {
+        self.0
+            .fetch_add(by.as_millis() as i64, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    pub fn set(&self, now_ms: i64) {
+        self.0.store(now_ms, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now_ms(&self) -> i64 {
+        self.0.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
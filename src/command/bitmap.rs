@@ -0,0 +1,879 @@
+use bytes::{Bytes, BytesMut};
+
+use crate::config::Config;
+use crate::resp::RespConcreteType;
+use crate::storage::{StorageValue, Value};
+
+use super::{
+    parse_int, typed, typed_or_create, CommandError, CommandFlag::*, CommandResult, CommandSpec,
+    Context,
+};
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("setbit", 4, setbit)
+        .flags(&[Write, Denyoom])
+        .keys(1, 1, 1)
+        .docs(
+            "bitmap",
+            "2.2.0",
+            "Sets or clears the bit at offset of the string value. Creates the key if it doesn't exist.",
+        ),
+    CommandSpec::new("getbit", 3, getbit)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("bitmap", "2.2.0", "Returns a bit value by offset."),
+    CommandSpec::new("bitcount", -2, bitcount)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs(
+            "bitmap",
+            "2.6.0",
+            "Counts the number of set bits (population counting) in a string.",
+        ),
+    CommandSpec::new("bitpos", -3, bitpos)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs(
+            "bitmap",
+            "2.8.7",
+            "Finds the first set (1) or clear (0) bit in a string.",
+        ),
+    CommandSpec::new("bitop", -4, bitop)
+        .flags(&[Write, Denyoom])
+        .keys(2, -1, 1)
+        .docs(
+            "bitmap",
+            "2.6.0",
+            "Performs bitwise operations on multiple strings, and stores the result.",
+        ),
+    CommandSpec::new("bitfield", -2, bitfield)
+        .flags(&[Write, Denyoom])
+        .keys(1, 1, 1)
+        .docs(
+            "bitmap",
+            "3.2.0",
+            "Performs arbitrary bitfield integer operations on strings.",
+        ),
+    CommandSpec::new("bitfield_ro", -2, bitfield_ro)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "bitmap",
+            "6.0.0",
+            "Performs arbitrary read-only bitfield integer operations on strings.",
+        ),
+];
+
+// bits are numbered from the most significant bit of the first byte
+fn get_bit(bytes: &[u8], offset: u64) -> bool {
+    bytes
+        .get((offset / 8) as usize)
+        .is_some_and(|byte| byte & (0x80 >> (offset % 8)) != 0)
+}
+
+fn set_bit(bytes: &mut [u8], offset: u64, bit: bool) {
+    let byte = &mut bytes[(offset / 8) as usize];
+    match bit {
+        true => *byte |= 0x80 >> (offset % 8),
+        false => *byte &= !(0x80 >> (offset % 8)),
+    }
+}
+
+// offsets are bounded so that the string they address fits in proto-max-bulk-len
+fn parse_offset(arg: &[u8], config: &Config) -> Result<u64, CommandError> {
+    match parse_int(arg) {
+        Ok(offset) if offset >= 0 && (offset as u64 >> 3) < config.proto.max_bulk_len as u64 => {
+            Ok(offset as u64)
+        }
+        _ => Err(CommandError::BitOffset),
+    }
+}
+
+fn empty() -> Value {
+    Value::String(BytesMut::new())
+}
+
+// the string at `key`, zero padded to at least `len` bytes and written to in place,
+// created when missing and keeping its ttl otherwise
+fn buffer<'a>(
+    ctx: &'a mut Context,
+    key: &Bytes,
+    len: usize,
+) -> Result<&'a mut BytesMut, CommandError> {
+    let bytes = typed_or_create(ctx.db, key, Value::as_string, empty)?;
+    if bytes.len() < len {
+        bytes.resize(len, 0);
+    }
+    Ok(bytes)
+}
+
+fn setbit(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let key = &argv[1];
+    let offset = parse_offset(&argv[2], ctx.config)?;
+    let bit = match argv[3].as_ref() {
+        b"0" => false,
+        b"1" => true,
+        _ => return Err(CommandError::BitValue),
+    };
+
+    let bytes = buffer(ctx, key, (offset / 8) as usize + 1)?;
+    let old = get_bit(bytes, offset);
+    set_bit(bytes, offset, bit);
+    Ok(RespConcreteType::Int(old as i64))
+}
+
+fn getbit(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let offset = parse_offset(&argv[2], ctx.config)?;
+    let bit =
+        typed(ctx.db, &argv[1], Value::as_string)?.is_some_and(|string| get_bit(string, offset));
+    Ok(RespConcreteType::Int(bit as i64))
+}
+
+/// A `start end [BYTE|BIT]` range resolved against a string of `len` bytes into an inclusive
+/// range of bits, `None` when it selects nothing. Negative indexes count from the end
+fn bit_range(args: &[Bytes], len: usize) -> Result<Option<(u64, u64)>, CommandError> {
+    let bits = match args.get(2).map(|unit| unit.to_ascii_lowercase()) {
+        None => false,
+        Some(unit) if unit == b"byte" => false,
+        Some(unit) if unit == b"bit" => true,
+        Some(_) => return Err(CommandError::Syntax),
+    };
+    let (mut start, mut end) = match args {
+        [] => (0, -1),
+        [start] => (parse_int(start)?, -1),
+        [start, end, ..] => (parse_int(start)?, parse_int(end)?),
+    };
+
+    let total = match bits {
+        true => len as i64 * 8,
+        false => len as i64,
+    };
+    if start < 0 {
+        start = (total + start).max(0);
+    }
+    if end < 0 {
+        end = (total + end).max(0);
+    }
+    end = end.min(total - 1);
+    if start > end || total == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(match bits {
+        true => (start as u64, end as u64),
+        false => (start as u64 * 8, end as u64 * 8 + 7),
+    }))
+}
+
+fn bitcount(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    // a start without an end is not a range
+    if argv.len() == 3 || argv.len() > 5 {
+        return Err(CommandError::Syntax);
+    }
+    let string = typed(ctx.db, &argv[1], Value::as_string)?.map_or(&[][..], |string| &string[..]);
+    let Some((from, to)) = bit_range(&argv[2..], string.len())? else {
+        return Ok(RespConcreteType::Int(0));
+    };
+
+    // whole bytes are counted at once, the partial ones at the edges bit by bit
+    let (first, last) = ((from / 8) as usize, (to / 8) as usize);
+    let mut count: u64 = string[first..=last]
+        .iter()
+        .map(|byte| byte.count_ones() as u64)
+        .sum();
+    count -= (first as u64 * 8..from)
+        .filter(|&bit| get_bit(string, bit))
+        .count() as u64;
+    count -= (to + 1..last as u64 * 8 + 8)
+        .filter(|&bit| get_bit(string, bit))
+        .count() as u64;
+    Ok(RespConcreteType::Int(count as i64))
+}
+
+fn bitpos(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    if argv.len() > 6 {
+        return Err(CommandError::Syntax);
+    }
+    let bit = match argv[2].as_ref() {
+        b"0" => false,
+        b"1" => true,
+        _ => return Err(CommandError::BitArg),
+    };
+    let end_given = argv.len() >= 5;
+
+    let Some(string) = typed(ctx.db, &argv[1], Value::as_string)? else {
+        // a missing key is an endless run of clear bits
+        return Ok(RespConcreteType::Int(match bit {
+            true => -1,
+            false => 0,
+        }));
+    };
+    let Some((from, to)) = bit_range(&argv[3..], string.len())? else {
+        return Ok(RespConcreteType::Int(-1));
+    };
+
+    // skip whole bytes that can't hold the bit, then look at the bits of the one that can
+    let skip = match bit {
+        true => 0x00,
+        false => 0xff,
+    };
+    let mut offset = from;
+    while offset <= to {
+        if offset % 8 == 0 && to - offset >= 7 && string[(offset / 8) as usize] == skip {
+            offset += 8;
+            continue;
+        }
+        if get_bit(string, offset) == bit {
+            return Ok(RespConcreteType::Int(offset as i64));
+        }
+        offset += 1;
+    }
+
+    // looking for a clear bit past the end of the string finds the zero padding,
+    // unless an explicit end said to stop before it
+    Ok(RespConcreteType::Int(match bit || end_given {
+        true => -1,
+        false => string.len() as i64 * 8,
+    }))
+}
+
+fn bitop(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let operation = argv[1].to_ascii_lowercase();
+    let destination = &argv[2];
+    let keys = &argv[3..];
+
+    if !matches!(operation.as_slice(), b"and" | b"or" | b"xor" | b"not") {
+        return Err(CommandError::Syntax);
+    }
+    if operation == b"not" && keys.len() != 1 {
+        return Err(CommandError::BitopNot);
+    }
+
+    // the sources are read where they are, a missing key is an empty string
+    for key in keys {
+        typed(ctx.db, key, Value::as_string)?;
+    }
+    let db = &*ctx.db;
+    let sources: Vec<&[u8]> = keys
+        .iter()
+        .map(|key| match db.get(key) {
+            Some(StorageValue {
+                value: Value::String(string),
+                ..
+            }) => &string[..],
+            _ => &[],
+        })
+        .collect();
+
+    // shorter sources are zero padded up to the longest one
+    let len = sources.iter().map(|source| source.len()).max().unwrap_or(0);
+    let result: BytesMut = (0..len)
+        .map(|i| {
+            let mut bytes = sources
+                .iter()
+                .map(|source| source.get(i).copied().unwrap_or(0));
+            let first = bytes.next().unwrap_or(0);
+            match operation.as_slice() {
+                b"and" => bytes.fold(first, |acc, byte| acc & byte),
+                b"or" => bytes.fold(first, |acc, byte| acc | byte),
+                b"xor" => bytes.fold(first, |acc, byte| acc ^ byte),
+                _ => !first,
+            }
+        })
+        .collect();
+
+    // an empty result deletes the destination instead of storing an empty string
+    match result.is_empty() {
+        true => {
+            ctx.db.remove(destination);
+        }
+        false => {
+            ctx.db.insert(
+                destination.clone(),
+                StorageValue::new(Value::String(result), None),
+            );
+        }
+    }
+    Ok(RespConcreteType::Int(len as i64))
+}
+
+#[derive(Clone, Copy)]
+enum Overflow {
+    Wrap,
+    Sat,
+    Fail,
+}
+
+#[derive(Clone, Copy)]
+struct Field {
+    signed: bool,
+    width: u32,
+    offset: u64,
+}
+
+impl Field {
+    fn parse(kind: &[u8], offset: &[u8], config: &Config) -> Result<Field, CommandError> {
+        let (signed, width) = match kind.split_first() {
+            Some((b'i' | b'I', width)) => (true, width),
+            Some((b'u' | b'U', width)) => (false, width),
+            _ => return Err(CommandError::BitfieldType),
+        };
+        let width = std::str::from_utf8(width)
+            .ok()
+            .and_then(|width| width.parse::<u32>().ok())
+            .filter(|&width| width >= 1 && width <= if signed { 64 } else { 63 })
+            .ok_or(CommandError::BitfieldType)?;
+
+        // `#n` addresses the n-th field of this width
+        let offset = match offset.strip_prefix(b"#") {
+            Some(index) => parse_offset(index, config)?
+                .checked_mul(width as u64)
+                .ok_or(CommandError::BitOffset)?,
+            None => parse_offset(offset, config)?,
+        };
+        if (offset + width as u64 - 1) >> 3 >= config.proto.max_bulk_len as u64 {
+            return Err(CommandError::BitOffset);
+        }
+        Ok(Field {
+            signed,
+            width,
+            offset,
+        })
+    }
+
+    fn min(&self) -> i128 {
+        match self.signed {
+            true => -(1 << (self.width - 1)),
+            false => 0,
+        }
+    }
+
+    fn max(&self) -> i128 {
+        match self.signed {
+            true => (1 << (self.width - 1)) - 1,
+            false => (1 << self.width) - 1,
+        }
+    }
+
+    fn get(&self, bytes: &[u8]) -> i64 {
+        let mut value: u64 = 0;
+        for i in 0..self.width as u64 {
+            value = value << 1 | get_bit(bytes, self.offset + i) as u64;
+        }
+        // sign extend from the field's top bit
+        match self.signed && self.width < 64 {
+            true => ((value << (64 - self.width)) as i64) >> (64 - self.width),
+            false => value as i64,
+        }
+    }
+
+    fn set(&self, bytes: &mut [u8], value: i64) {
+        for i in 0..self.width as u64 {
+            let bit = (value as u64 >> (self.width as u64 - 1 - i)) & 1 == 1;
+            set_bit(bytes, self.offset + i, bit);
+        }
+    }
+
+    /// Fits `value` into the field according to `overflow`, `None` when it fails
+    fn fit(&self, value: i128, overflow: Overflow) -> Option<i64> {
+        if (self.min()..=self.max()).contains(&value) {
+            return Some(value as i64);
+        }
+        match overflow {
+            Overflow::Wrap => {
+                let wrapped = value.rem_euclid(1 << self.width);
+                Some(match wrapped > self.max() {
+                    true => (wrapped - (1 << self.width)) as i64,
+                    false => wrapped as i64,
+                })
+            }
+            Overflow::Sat => Some(match value > self.max() {
+                true => self.max() as i64,
+                false => self.min() as i64,
+            }),
+            Overflow::Fail => None,
+        }
+    }
+}
+
+enum FieldOp {
+    Get(Field),
+    Set(Field, i64, Overflow),
+    IncrBy(Field, i64, Overflow),
+}
+
+fn field_ops(argv: &[Bytes], config: &Config) -> Result<Vec<FieldOp>, CommandError> {
+    let mut ops = Vec::new();
+    let mut overflow = Overflow::Wrap;
+
+    let mut args = argv[2..].iter();
+    while let Some(op) = args.next() {
+        let op = op.to_ascii_lowercase();
+        if op == b"overflow" {
+            let kind = args.next().ok_or(CommandError::Syntax)?;
+            overflow = match kind.to_ascii_lowercase().as_slice() {
+                b"wrap" => Overflow::Wrap,
+                b"sat" => Overflow::Sat,
+                b"fail" => Overflow::Fail,
+                _ => return Err(CommandError::OverflowType),
+            };
+            continue;
+        }
+
+        let arity = match op.as_slice() {
+            b"get" => 2,
+            b"set" | b"incrby" => 3,
+            _ => return Err(CommandError::Syntax),
+        };
+        let args: Vec<&Bytes> = args.by_ref().take(arity).collect();
+        if args.len() < arity {
+            return Err(CommandError::Syntax);
+        }
+
+        let field = Field::parse(args[0], args[1], config)?;
+        ops.push(match op.as_slice() {
+            b"get" => FieldOp::Get(field),
+            b"set" => FieldOp::Set(field, parse_int(args[2])?, overflow),
+            _ => FieldOp::IncrBy(field, parse_int(args[2])?, overflow),
+        });
+    }
+    Ok(ops)
+}
+
+fn bitfield(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let ops = field_ops(argv, ctx.config)?;
+    run_field_ops(ctx, &argv[1], ops)
+}
+
+fn bitfield_ro(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let ops = field_ops(argv, ctx.config)?;
+    if !ops.iter().all(|op| matches!(op, FieldOp::Get(_))) {
+        return Err(CommandError::BitfieldReadonly);
+    }
+    run_field_ops(ctx, &argv[1], ops)
+}
+
+fn run_field_ops(ctx: &mut Context, key: &Bytes, ops: Vec<FieldOp>) -> CommandResult {
+    // the string grows up front to fit every field that is written, even one that then fails
+    let writes = ops.iter().filter_map(|op| match op {
+        FieldOp::Get(_) => None,
+        FieldOp::Set(field, ..) | FieldOp::IncrBy(field, ..) => Some(field),
+    });
+    let len = writes
+        .clone()
+        .map(|field| ((field.offset + field.width as u64 + 7) / 8) as usize)
+        .max();
+    // reads alone neither create the key nor pad it
+    let mut missing = BytesMut::new();
+    let bytes = match len {
+        Some(len) => buffer(ctx, key, len)?,
+        None => typed(ctx.db, key, Value::as_string)?.unwrap_or(&mut missing),
+    };
+
+    let mut replies = Vec::with_capacity(ops.len());
+    for op in ops {
+        replies.push(match op {
+            FieldOp::Get(field) => RespConcreteType::Int(field.get(bytes)),
+            FieldOp::Set(field, value, overflow) => {
+                // an unsigned field takes the value's bits as an unsigned number
+                let value = match field.signed {
+                    true => value as i128,
+                    false => value as u64 as i128,
+                };
+                match field.fit(value, overflow) {
+                    Some(value) => {
+                        let old = field.get(bytes);
+                        field.set(bytes, value);
+                        RespConcreteType::Int(old)
+                    }
+                    None => RespConcreteType::NullBulkString,
+                }
+            }
+            FieldOp::IncrBy(field, increment, overflow) => {
+                let value = field.get(bytes) as i128 + increment as i128;
+                match field.fit(value, overflow) {
+                    Some(value) => {
+                        field.set(bytes, value);
+                        RespConcreteType::Int(value)
+                    }
+                    None => RespConcreteType::NullBulkString,
+                }
+            }
+        });
+    }
+
+    Ok(RespConcreteType::array(replies))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command::tests::Server;
+
+    // the integers of an array reply, nil ones as `None`
+    fn ints(reply: &str) -> Vec<Option<i64>> {
+        reply
+            .split("\r\n")
+            .skip(1)
+            .filter(|line| !line.is_empty())
+            .map(|line| match line {
+                "$-1" => None,
+                line => Some(line.strip_prefix(':').unwrap().parse().unwrap()),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn setbit_and_getbit() {
+        let mut server = Server::new();
+        assert_eq!(server.run(&["SETBIT", "k", "7", "1"]).await, ":0\r\n");
+        assert_eq!(server.run(&["SETBIT", "k", "7", "0"]).await, ":1\r\n");
+        assert_eq!(server.run(&["SETBIT", "k", "1", "1"]).await, ":0\r\n");
+        assert_eq!(server.run(&["GET", "k"]).await, "$1\r\n@\r\n");
+        // the string grows with zero bytes up to the bit
+        assert_eq!(server.run(&["SETBIT", "k", "100", "1"]).await, ":0\r\n");
+        assert_eq!(server.run(&["STRLEN", "k"]).await, ":13\r\n");
+        assert_eq!(server.run(&["GETBIT", "k", "100"]).await, ":1\r\n");
+        assert_eq!(server.run(&["GETBIT", "k", "99"]).await, ":0\r\n");
+        assert_eq!(server.run(&["GETBIT", "k", "10000"]).await, ":0\r\n");
+        assert_eq!(server.run(&["GETBIT", "missing", "0"]).await, ":0\r\n");
+
+        assert_eq!(
+            server.run(&["SETBIT", "k", "0", "2"]).await,
+            "-ERR bit is not an integer or out of range\r\n"
+        );
+        for offset in ["-1", "4294967296", "x"] {
+            assert_eq!(
+                server.run(&["SETBIT", "k", offset, "1"]).await,
+                "-ERR bit offset is not an integer or out of range\r\n"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn bitcount_ranges() {
+        let mut server = Server::new();
+        assert_eq!(server.run(&["SET", "k", "foobar"]).await, "+OK\r\n");
+        assert_eq!(server.run(&["BITCOUNT", "k"]).await, ":26\r\n");
+        assert_eq!(server.run(&["BITCOUNT", "k", "0", "0"]).await, ":4\r\n");
+        assert_eq!(
+            server.run(&["BITCOUNT", "k", "1", "1", "BYTE"]).await,
+            ":6\r\n"
+        );
+        assert_eq!(server.run(&["BITCOUNT", "k", "-1", "-1"]).await, ":4\r\n");
+        assert_eq!(
+            server.run(&["BITCOUNT", "k", "-100", "100"]).await,
+            ":26\r\n"
+        );
+        assert_eq!(server.run(&["BITCOUNT", "k", "2", "1"]).await, ":0\r\n");
+        // bit ranges may start and end inside a byte
+        assert_eq!(
+            server.run(&["BITCOUNT", "k", "5", "30", "BIT"]).await,
+            ":17\r\n"
+        );
+        assert_eq!(
+            server.run(&["BITCOUNT", "k", "-8", "-1", "bit"]).await,
+            ":4\r\n"
+        );
+        assert_eq!(
+            server.run(&["BITCOUNT", "k", "1", "1", "BIT"]).await,
+            ":1\r\n"
+        );
+        assert_eq!(server.run(&["BITCOUNT", "missing"]).await, ":0\r\n");
+
+        assert_eq!(
+            server.run(&["BITCOUNT", "k", "0"]).await,
+            "-ERR syntax error\r\n"
+        );
+        assert_eq!(
+            server.run(&["BITCOUNT", "k", "0", "1", "BITS"]).await,
+            "-ERR syntax error\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn bitpos_edges() {
+        let mut server = Server::new();
+        // ff f0 00
+        server
+            .run(&["BITFIELD", "k", "SET", "u24", "0", "16773120"])
+            .await;
+        assert_eq!(server.run(&["BITPOS", "k", "0"]).await, ":12\r\n");
+        assert_eq!(server.run(&["BITPOS", "k", "1", "2"]).await, ":-1\r\n");
+
+        // 00 ff f0
+        server
+            .run(&["BITFIELD", "k", "SET", "u24", "0", "65520"])
+            .await;
+        assert_eq!(server.run(&["BITPOS", "k", "1"]).await, ":8\r\n");
+        assert_eq!(server.run(&["BITPOS", "k", "1", "2"]).await, ":16\r\n");
+        assert_eq!(
+            server.run(&["BITPOS", "k", "1", "2", "-1", "BYTE"]).await,
+            ":16\r\n"
+        );
+        assert_eq!(
+            server.run(&["BITPOS", "k", "1", "7", "15", "BIT"]).await,
+            ":8\r\n"
+        );
+        assert_eq!(
+            server.run(&["BITPOS", "k", "1", "7", "-3", "BIT"]).await,
+            ":8\r\n"
+        );
+        assert_eq!(
+            server.run(&["BITPOS", "k", "0", "8", "19", "BIT"]).await,
+            ":-1\r\n"
+        );
+        assert_eq!(
+            server.run(&["BITPOS", "k", "0", "8", "20", "BIT"]).await,
+            ":20\r\n"
+        );
+
+        // all set: a clear bit is found past the end only when no end was given
+        server
+            .run(&["BITFIELD", "k", "SET", "u24", "0", "16777215"])
+            .await;
+        assert_eq!(server.run(&["BITPOS", "k", "0"]).await, ":24\r\n");
+        assert_eq!(server.run(&["BITPOS", "k", "0", "1"]).await, ":24\r\n");
+        assert_eq!(
+            server.run(&["BITPOS", "k", "0", "0", "-1"]).await,
+            ":-1\r\n"
+        );
+
+        server.run(&["BITFIELD", "k", "SET", "u24", "0", "0"]).await;
+        assert_eq!(server.run(&["BITPOS", "k", "1"]).await, ":-1\r\n");
+        assert_eq!(server.run(&["BITPOS", "missing", "0"]).await, ":0\r\n");
+        assert_eq!(server.run(&["BITPOS", "missing", "1"]).await, ":-1\r\n");
+        assert_eq!(
+            server.run(&["BITPOS", "k", "2"]).await,
+            "-ERR The bit argument must be 1 or 0.\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn bitfield_overflow_modes() {
+        let mut server = Server::new();
+        // the default wraps around
+        assert_eq!(
+            ints(
+                &server
+                    .run(&["BITFIELD", "k", "SET", "u8", "0", "255"])
+                    .await
+            ),
+            [Some(0)]
+        );
+        assert_eq!(
+            ints(
+                &server
+                    .run(&["BITFIELD", "k", "INCRBY", "u8", "0", "10"])
+                    .await
+            ),
+            [Some(9)]
+        );
+        assert_eq!(
+            ints(
+                &server
+                    .run(&["BITFIELD", "k", "SET", "i8", "0", "127"])
+                    .await
+            ),
+            [Some(9)]
+        );
+        assert_eq!(
+            ints(
+                &server
+                    .run(&["BITFIELD", "k", "INCRBY", "i8", "0", "1"])
+                    .await
+            ),
+            [Some(-128)]
+        );
+        assert_eq!(
+            ints(
+                &server
+                    .run(&["BITFIELD", "k", "OVERFLOW", "WRAP", "INCRBY", "i8", "0", "-1"])
+                    .await
+            ),
+            [Some(127)]
+        );
+
+        // saturation sticks at the bounds
+        assert_eq!(
+            ints(
+                &server
+                    .run(&["BITFIELD", "k", "OVERFLOW", "SAT", "INCRBY", "i8", "0", "100"])
+                    .await
+            ),
+            [Some(127)]
+        );
+        assert_eq!(
+            ints(
+                &server
+                    .run(&["BITFIELD", "k", "OVERFLOW", "SAT", "INCRBY", "i8", "0", "-1000"])
+                    .await
+            ),
+            [Some(-128)]
+        );
+        assert_eq!(
+            ints(
+                &server
+                    .run(&["BITFIELD", "k", "OVERFLOW", "SAT", "SET", "u8", "0", "300"])
+                    .await
+            ),
+            [Some(128)]
+        );
+        assert_eq!(
+            ints(&server.run(&["BITFIELD", "k", "GET", "u8", "0"]).await),
+            [Some(255)]
+        );
+
+        // a failed operation leaves the field alone, the overflow mode holds until changed
+        assert_eq!(
+            ints(
+                &server
+                    .run(&[
+                        "BITFIELD", "k", "OVERFLOW", "FAIL", "INCRBY", "u8", "0", "1", "SET", "u8",
+                        "0", "256", "OVERFLOW", "WRAP", "INCRBY", "u8", "0", "1"
+                    ])
+                    .await
+            ),
+            [None, None, Some(0)]
+        );
+
+        // the example from the redis docs
+        for expected in [[1, 1], [2, 2], [3, 3], [0, 3]] {
+            assert_eq!(
+                ints(
+                    &server
+                        .run(&[
+                            "BITFIELD", "c", "INCRBY", "u2", "100", "1", "OVERFLOW", "SAT",
+                            "INCRBY", "u2", "102", "1"
+                        ])
+                        .await
+                ),
+                expected.map(Some)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn bitfield_widths_and_offsets() {
+        let mut server = Server::new();
+        // the same bits read signed and unsigned
+        assert_eq!(
+            ints(&server.run(&["BITFIELD", "k", "SET", "i8", "0", "-1"]).await),
+            [Some(0)]
+        );
+        assert_eq!(
+            ints(
+                &server
+                    .run(&[
+                        "BITFIELD", "k", "GET", "u8", "0", "GET", "i4", "0", "GET", "u4", "4",
+                        "GET", "i1", "0"
+                    ])
+                    .await
+            ),
+            [Some(255), Some(-1), Some(15), Some(-1)]
+        );
+
+        // `#n` is the n-th field of the width
+        assert_eq!(
+            ints(
+                &server
+                    .run(&["BITFIELD", "k", "SET", "u8", "#1", "200"])
+                    .await
+            ),
+            [Some(0)]
+        );
+        assert_eq!(
+            ints(
+                &server
+                    .run(&["BITFIELD", "k", "GET", "u8", "8", "GET", "u4", "#3", "GET", "u8", "#2"])
+                    .await
+            ),
+            [Some(200), Some(8), Some(0)]
+        );
+        // fields straddle byte boundaries
+        assert_eq!(
+            ints(&server.run(&["BITFIELD", "k", "GET", "u8", "4"]).await),
+            [Some(252)]
+        );
+
+        // the widest of each kind
+        assert_eq!(
+            ints(
+                &server
+                    .run(&[
+                        "BITFIELD",
+                        "w",
+                        "SET",
+                        "i64",
+                        "0",
+                        "9223372036854775807",
+                        "INCRBY",
+                        "i64",
+                        "0",
+                        "1"
+                    ])
+                    .await
+            ),
+            [Some(0), Some(i64::MIN)]
+        );
+        assert_eq!(
+            ints(
+                &server
+                    .run(&[
+                        "BITFIELD", "w", "SET", "u63", "1", "-1", "GET", "u63", "1", "GET", "i64",
+                        "0"
+                    ])
+                    .await
+            ),
+            [Some(0), Some(i64::MAX), Some(-1)]
+        );
+
+        // reads alone don't create the key
+        assert_eq!(
+            ints(
+                &server
+                    .run(&["BITFIELD_RO", "missing", "GET", "u8", "0"])
+                    .await
+            ),
+            [Some(0)]
+        );
+        assert_eq!(server.run(&["EXISTS", "missing"]).await, ":0\r\n");
+        // writes size the string for every field, even one that fails
+        server
+            .run(&[
+                "BITFIELD", "f", "OVERFLOW", "FAIL", "INCRBY", "u8", "16", "256",
+            ])
+            .await;
+        assert_eq!(server.run(&["STRLEN", "f"]).await, ":3\r\n");
+    }
+
+    #[tokio::test]
+    async fn bitfield_errors() {
+        let mut server = Server::new();
+        let invalid_type = "-ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.\r\n";
+        for kind in ["u64", "i65", "i0", "x8", "u"] {
+            assert_eq!(
+                server.run(&["BITFIELD", "k", "GET", kind, "0"]).await,
+                invalid_type
+            );
+        }
+        assert_eq!(
+            server.run(&["BITFIELD", "k", "GET", "u8", "-1"]).await,
+            "-ERR bit offset is not an integer or out of range\r\n"
+        );
+        assert_eq!(
+            server.run(&["BITFIELD", "k", "OVERFLOW", "NONE"]).await,
+            "-ERR Invalid OVERFLOW type specified\r\n"
+        );
+        assert_eq!(
+            server.run(&["BITFIELD", "k", "SET", "u8", "0"]).await,
+            "-ERR syntax error\r\n"
+        );
+        assert_eq!(
+            server
+                .run(&["BITFIELD_RO", "k", "SET", "u8", "0", "1"])
+                .await,
+            "-ERR BITFIELD_RO only supports the GET subcommand\r\n"
+        );
+        assert_eq!(server.run(&["EXISTS", "k"]).await, ":0\r\n");
+    }
+}
@@ -0,0 +1,116 @@
+use bytes::Bytes;
+
+use crate::resp::{RespConcreteType, RespVersion};
+
+use super::{parse_int, CommandError, CommandFlag::*, CommandResult, CommandSpec, Context};
+
+pub const VERSION: &str = "7.2.4";
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("ping", -1, ping)
+        .flags(&[Fast, Stale])
+        .docs(
+            "connection",
+            "1.0.0",
+            "Returns the server's liveliness response.",
+        ),
+    CommandSpec::new("echo", 2, echo)
+        .flags(&[Fast, Stale, Loading])
+        .docs("connection", "1.0.0", "Returns the given string."),
+    CommandSpec::new("hello", -1, hello)
+        .flags(&[Noscript, Loading, Stale, Fast, NoAuth])
+        .docs("connection", "6.0.0", "Handshakes with the Redis server."),
+    CommandSpec::new("quit", -1, quit)
+        .flags(&[Noscript, Loading, Stale, Fast, NoAuth])
+        .docs("connection", "1.0.0", "Closes the connection."),
+    CommandSpec::new("reset", 1, reset)
+        .flags(&[Noscript, Loading, Stale, Fast, NoAuth])
+        .docs("connection", "6.2.0", "Resets the connection."),
+];
+
+fn ping(_ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    match argv {
+        [_] => Ok(RespConcreteType::simple("PONG")),
+        [_, message] => Ok(RespConcreteType::BulkString(message.clone())),
+        _ => Err(CommandError::WrongArity("ping".to_string())),
+    }
+}
+
+fn echo(_ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    Ok(RespConcreteType::BulkString(argv[1].clone()))
+}
+
+fn hello(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let protocol = match argv.get(1) {
+        None => ctx.client.protocol,
+        Some(protover) => match parse_int(protover).map_err(|_| CommandError::ProtoVersion)? {
+            2 => RespVersion::Resp2,
+            3 => RespVersion::Resp3,
+            _ => return Err(CommandError::NoProto),
+        },
+    };
+
+    // AUTH and SETNAME are accepted but ignored, there are no users or client names
+    let mut options = argv.iter().skip(2);
+    while let Some(option) = options.next() {
+        let option_args = match option.to_ascii_lowercase().as_slice() {
+            b"auth" => 2,
+            b"setname" => 1,
+            _ => 0,
+        };
+        if option_args == 0 || options.by_ref().take(option_args).count() < option_args {
+            return Err(CommandError::HelloOption(
+                String::from_utf8_lossy(option).to_string(),
+            ));
+        }
+    }
+
+    ctx.client.protocol = protocol;
+
+    // HELLO replies with a map of server properties, which the encoder flattens under RESP2
+    let proto = match protocol {
+        RespVersion::Resp2 => 2,
+        RespVersion::Resp3 => 3,
+    };
+    Ok(RespConcreteType::Map(vec![
+        (
+            RespConcreteType::bulk("server"),
+            RespConcreteType::bulk("redis"),
+        ),
+        (
+            RespConcreteType::bulk("version"),
+            RespConcreteType::bulk(VERSION),
+        ),
+        (
+            RespConcreteType::bulk("proto"),
+            RespConcreteType::Int(proto),
+        ),
+        (
+            RespConcreteType::bulk("id"),
+            RespConcreteType::Int(ctx.client.id as i64),
+        ),
+        (
+            RespConcreteType::bulk("mode"),
+            RespConcreteType::bulk("standalone"),
+        ),
+        (
+            RespConcreteType::bulk("role"),
+            RespConcreteType::bulk("master"),
+        ),
+        (
+            RespConcreteType::bulk("modules"),
+            RespConcreteType::array([]),
+        ),
+    ]))
+}
+
+fn quit(ctx: &mut Context, _argv: &[Bytes]) -> CommandResult {
+    // the connection is closed by `process` once this reply is written
+    ctx.client.closing = true;
+    Ok(RespConcreteType::ok())
+}
+
+fn reset(ctx: &mut Context, _argv: &[Bytes]) -> CommandResult {
+    ctx.client.protocol = RespVersion::Resp2;
+    Ok(RespConcreteType::simple("RESET"))
+}
@@ -0,0 +1,147 @@
+use bytes::Bytes;
+use thiserror::Error;
+
+/// Errors surfaced to clients, the message is the exact text of the `-` reply
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error(
+        "ERR unknown command '{}', with args beginning with: {}",
+        truncate(&String::from_utf8_lossy(.0), 128),
+        quoted_args(.1)
+    )]
+    UnknownCommand(Bytes, Vec<Bytes>),
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
+    #[error("ERR value is not an integer or out of range")]
+    NotInteger,
+    #[error("ERR value is not a valid float")]
+    NotFloat,
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
+    #[error("ERR decrement would overflow")]
+    DecrementOverflow,
+    #[error("ERR increment would produce NaN or Infinity")]
+    NanOrInfinity,
+    #[error("ERR value is NaN or Infinity")]
+    ValueNanOrInfinity,
+    #[error("ERR string exceeds maximum allowed size (proto-max-bulk-len)")]
+    StringTooLong,
+    #[error("ERR offset is out of range")]
+    OffsetOutOfRange,
+    #[error("ERR The specified keys must contain string values")]
+    LcsNotString,
+    #[error("ERR If you want both the length and indexes, please just use IDX.")]
+    LcsLenAndIdx,
+    #[error("ERR Insufficient memory, transient memory for LCS exceeds proto-max-bulk-len")]
+    LcsTooLarge,
+    #[error("ERR bit offset is not an integer or out of range")]
+    BitOffset,
+    #[error("ERR bit is not an integer or out of range")]
+    BitValue,
+    #[error("ERR The bit argument must be 1 or 0.")]
+    BitArg,
+    #[error("ERR BITOP NOT must be called with a single source key.")]
+    BitopNot,
+    #[error("ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.")]
+    BitfieldType,
+    #[error("ERR Invalid OVERFLOW type specified")]
+    OverflowType,
+    #[error("ERR BITFIELD_RO only supports the GET subcommand")]
+    BitfieldReadonly,
+    #[error("ERR index out of range")]
+    IndexOutOfRange,
+    #[error("ERR value is out of range, must be positive")]
+    MustBePositive,
+    #[error("ERR value is out of range, value must between {0} and {1}")]
+    ValueOutOfRange(i64, i64),
+    #[error("ERR Insufficient memory, the reply to this count would not fit")]
+    CountTooLarge,
+    #[error("ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the last match")]
+    RankZero,
+    #[error("ERR COUNT can't be negative")]
+    CountNegative,
+    #[error("ERR MAXLEN can't be negative")]
+    MaxlenNegative,
+    #[error("ERR numkeys should be greater than 0")]
+    NumKeys,
+    #[error("ERR Number of keys can't be greater than number of args")]
+    NumKeysTooMany,
+    #[error("ERR LIMIT can't be negative")]
+    LimitNegative,
+    #[error("ERR count should be greater than 0")]
+    CountPositive,
+    #[error("ERR hash value is not an integer")]
+    HashNotInteger,
+    #[error("ERR hash value is not a float")]
+    HashNotFloat,
+    #[error("ERR Mandatory argument FIELDS is missing or not at the right position")]
+    FieldsMissing,
+    #[error("ERR Number of fields must be a positive integer")]
+    NumFieldsPositive,
+    #[error("ERR The `numfields` parameter must match the number of arguments")]
+    NumFieldsMismatch,
+    #[error("ERR invalid expire time, must be >= 0")]
+    NegativeExpireTime,
+    #[error("ERR timeout is not a float or out of range")]
+    TimeoutNotFloat,
+    #[error("ERR timeout is negative")]
+    TimeoutNegative,
+    #[error("ERR timeout is out of range")]
+    TimeoutOutOfRange,
+    #[error("ERR syntax error")]
+    Syntax,
+    #[error("ERR invalid expire time in '{0}' command")]
+    InvalidExpireTime(String),
+    #[error("ERR Unsupported option {0}")]
+    UnsupportedOption(String),
+    #[error("ERR {0} options at the same time are not compatible")]
+    IncompatibleExpireFlags(&'static str),
+    #[error("ERR no such key")]
+    NoSuchKey,
+    #[error("ERR source and destination objects are the same")]
+    SameObject,
+    #[error("ERR DB index is out of range")]
+    DbIndexOutOfRange,
+    #[error("ERR invalid cursor")]
+    InvalidCursor,
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+    #[error("ERR Protocol version is not an integer or out of range")]
+    ProtoVersion,
+    #[error("NOPROTO sorry, this protocol version is not supported.")]
+    NoProto,
+    #[error("ERR Syntax error in HELLO option '{0}'")]
+    HelloOption(String),
+    #[error(
+        "ERR unknown subcommand '{}'. Try {} HELP.",
+        truncate(&String::from_utf8_lossy(.1), 128),
+        .0.to_uppercase()
+    )]
+    UnknownSubcommand(String, Bytes),
+    #[error("ERR Invalid command specified")]
+    InvalidCommandSpecified,
+    #[error("ERR Invalid number of arguments specified for command")]
+    InvalidArgumentCount,
+    #[error("ERR The command has no key arguments")]
+    NoKeyArguments,
+}
+
+fn truncate(s: &str, max: usize) -> &str {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => &s[..i],
+        None => s,
+    }
+}
+
+// redis quotes arguments until the listing reaches 128 characters
+fn quoted_args(args: &[Bytes]) -> String {
+    let mut quoted = String::new();
+    for arg in args {
+        let len = quoted.chars().count();
+        if len >= 128 {
+            break;
+        }
+        quoted += &format!("'{}' ", truncate(&String::from_utf8_lossy(arg), 128 - len));
+    }
+    quoted
+}
@@ -0,0 +1,209 @@
+use bytes::Bytes;
+
+use crate::resp::RespConcreteType;
+
+use super::{parse_int, CommandError, CommandFlag::*, CommandResult, CommandSpec, Context};
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("expire", -3, expire)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "generic",
+            "1.0.0",
+            "Sets the expiration time of a key in seconds.",
+        ),
+    CommandSpec::new("pexpire", -3, pexpire)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "generic",
+            "2.6.0",
+            "Sets the expiration time of a key in milliseconds.",
+        ),
+    CommandSpec::new("expireat", -3, expireat)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "generic",
+            "1.2.0",
+            "Sets the expiration time of a key to a Unix timestamp.",
+        ),
+    CommandSpec::new("pexpireat", -3, pexpireat)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "generic",
+            "2.6.0",
+            "Sets the expiration time of a key to a Unix milliseconds timestamp.",
+        ),
+    CommandSpec::new("ttl", 2, ttl)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "generic",
+            "1.0.0",
+            "Returns the expiration time in seconds of a key.",
+        ),
+    CommandSpec::new("pttl", 2, pttl)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "generic",
+            "2.6.0",
+            "Returns the expiration time in milliseconds of a key.",
+        ),
+    CommandSpec::new("expiretime", 2, expiretime)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "generic",
+            "7.0.0",
+            "Returns the expiration time of a key as a Unix timestamp.",
+        ),
+    CommandSpec::new("pexpiretime", 2, pexpiretime)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "generic",
+            "7.0.0",
+            "Returns the expiration time of a key as a Unix milliseconds timestamp.",
+        ),
+    CommandSpec::new("persist", 2, persist)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs("generic", "2.2.0", "Removes the expiration time of a key."),
+];
+
+fn expire(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    expire_generic(ctx, argv, 1000, false)
+}
+
+fn pexpire(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    expire_generic(ctx, argv, 1, false)
+}
+
+fn expireat(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    expire_generic(ctx, argv, 1000, true)
+}
+
+fn pexpireat(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    expire_generic(ctx, argv, 1, true)
+}
+
+/// The NX, XX, GT and LT conditions of EXPIRE and HEXPIRE style commands
+#[derive(Default)]
+pub struct ExpireFlags {
+    nx: bool,
+    xx: bool,
+    gt: bool,
+    lt: bool,
+}
+
+impl ExpireFlags {
+    /// Whether a new deadline `when` may replace `current`. No ttl counts as expiring
+    /// never, so GT cannot apply to it and LT always does
+    pub fn applies(&self, current: Option<i64>, when: i64) -> bool {
+        match current {
+            None => !self.xx && !self.gt,
+            Some(current) => {
+                !self.nx && (!self.gt || when > current) && (!self.lt || when < current)
+            }
+        }
+    }
+}
+
+pub fn expire_flags(options: &[Bytes]) -> Result<ExpireFlags, CommandError> {
+    let mut flags = ExpireFlags::default();
+    for option in options {
+        match option.to_ascii_lowercase().as_slice() {
+            b"nx" => flags.nx = true,
+            b"xx" => flags.xx = true,
+            b"gt" => flags.gt = true,
+            b"lt" => flags.lt = true,
+            _ => {
+                return Err(CommandError::UnsupportedOption(
+                    String::from_utf8_lossy(option).to_string(),
+                ))
+            }
+        }
+    }
+
+    if flags.nx && (flags.xx || flags.gt || flags.lt) {
+        return Err(CommandError::IncompatibleExpireFlags("NX and XX, GT or LT"));
+    }
+    if flags.gt && flags.lt {
+        return Err(CommandError::IncompatibleExpireFlags("GT and LT"));
+    }
+    Ok(flags)
+}
+
+// `unit` is the number of milliseconds in one unit of the argument
+fn expire_generic(ctx: &mut Context, argv: &[Bytes], unit: i64, absolute: bool) -> CommandResult {
+    let invalid =
+        || CommandError::InvalidExpireTime(String::from_utf8_lossy(&argv[0]).to_lowercase());
+
+    let time = parse_int(&argv[2])?;
+    let flags = expire_flags(&argv[3..])?;
+
+    let now = ctx.db.now();
+    let when = time.checked_mul(unit).ok_or_else(invalid)?;
+    let when = match absolute {
+        true => when,
+        false => when.checked_add(now).ok_or_else(invalid)?,
+    };
+
+    let Some(value) = ctx.db.live(&argv[1]) else {
+        return Ok(RespConcreteType::Int(0));
+    };
+
+    if !flags.applies(value.expiry_at(), when) {
+        return Ok(RespConcreteType::Int(0));
+    }
+
+    // a deadline already in the past deletes the key right away
+    if when <= now {
+        ctx.db.remove(&argv[1]);
+        return Ok(RespConcreteType::Int(1));
+    }
+
+    ctx.db.expire(&argv[1], Some(when));
+    Ok(RespConcreteType::Int(1))
+}
+
+fn ttl(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    ttl_generic(ctx, argv, |expiry_at, now| (expiry_at - now + 500) / 1000)
+}
+
+fn pttl(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    ttl_generic(ctx, argv, |expiry_at, now| expiry_at - now)
+}
+
+fn expiretime(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    ttl_generic(ctx, argv, |expiry_at, _| expiry_at / 1000)
+}
+
+fn pexpiretime(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    ttl_generic(ctx, argv, |expiry_at, _| expiry_at)
+}
+
+// -2 for a missing key, -1 for a key without a ttl, otherwise `reply(expiry_at, now)`
+fn ttl_generic(ctx: &mut Context, argv: &[Bytes], reply: fn(i64, i64) -> i64) -> CommandResult {
+    let now = ctx.db.now();
+    let ttl = match ctx.db.live(&argv[1]) {
+        None => -2,
+        Some(value) => match value.expiry_at() {
+            None => -1,
+            Some(expiry_at) => reply(expiry_at, now.min(expiry_at)),
+        },
+    };
+    Ok(RespConcreteType::Int(ttl))
+}
+
+fn persist(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let removed = match ctx.db.live(&argv[1]) {
+        Some(_) => ctx.db.expire(&argv[1], None).flatten().is_some(),
+        None => false,
+    };
+    Ok(RespConcreteType::Int(removed as i64))
+}
@@ -0,0 +1,697 @@
+use bytes::Bytes;
+
+use crate::random;
+use crate::resp::{RespConcreteType, RespVersion};
+use crate::storage::{Hash, Value};
+
+use super::expire::expire_flags;
+use super::keyspace::{parse_cursor, scan_options, scan_reply, scan_walk};
+use super::string::{expire_at, format_float};
+use super::{
+    parse_float, parse_int, parse_int_in, typed, typed_or_create, CommandError, CommandFlag::*,
+    CommandResult, CommandSpec, Context,
+};
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("hset", -4, hset)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "2.0.0", "Creates or modifies the value of a field in a hash."),
+    CommandSpec::new("hsetnx", 4, hsetnx)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "2.0.0", "Sets the value of a field in a hash only when the field doesn't exist."),
+    CommandSpec::new("hget", 3, hget)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "2.0.0", "Returns the value of a field in a hash."),
+    CommandSpec::new("hmget", -3, hmget)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "2.0.0", "Returns the values of all fields in a hash."),
+    CommandSpec::new("hgetall", 2, hgetall)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs("hash", "2.0.0", "Returns all fields and values in a hash."),
+    CommandSpec::new("hkeys", 2, hkeys)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs("hash", "2.0.0", "Returns all fields in a hash."),
+    CommandSpec::new("hvals", 2, hvals)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs("hash", "2.0.0", "Returns all values in a hash."),
+    CommandSpec::new("hlen", 2, hlen)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "2.0.0", "Returns the number of fields in a hash."),
+    CommandSpec::new("hexists", 3, hexists)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "2.0.0", "Determines whether a field exists in a hash."),
+    CommandSpec::new("hdel", -3, hdel)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "2.0.0", "Deletes one or more fields and their values from a hash. Deletes the hash if no fields remain."),
+    CommandSpec::new("hstrlen", 3, hstrlen)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "3.2.0", "Returns the length of the value of a field."),
+    CommandSpec::new("hincrby", 4, hincrby)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "2.0.0", "Increments the integer value of a field in a hash by a number. Uses 0 as initial value if the field doesn't exist."),
+    CommandSpec::new("hincrbyfloat", 4, hincrbyfloat)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "2.6.0", "Increments the floating point value of a field by a number. Uses 0 as initial value if the field doesn't exist."),
+    CommandSpec::new("hrandfield", -2, hrandfield)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs("hash", "6.2.0", "Returns one or more random fields from a hash."),
+    CommandSpec::new("hscan", -3, hscan)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs("hash", "2.8.0", "Iterates over fields and values of a hash."),
+    CommandSpec::new("hexpire", -6, hexpire)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "7.4.0", "Set expiry for hash field using relative time to expire (seconds)"),
+    CommandSpec::new("hpexpire", -6, hpexpire)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "7.4.0", "Set expiry for hash field using relative time to expire (milliseconds)"),
+    CommandSpec::new("hexpireat", -6, hexpireat)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "7.4.0", "Set expiry for hash field using an absolute Unix timestamp (seconds)"),
+    CommandSpec::new("hpexpireat", -6, hpexpireat)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "7.4.0", "Set expiry for hash field using an absolute Unix timestamp (milliseconds)"),
+    CommandSpec::new("httl", -5, httl)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "7.4.0", "Returns the TTL in seconds of a hash field."),
+    CommandSpec::new("hpttl", -5, hpttl)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "7.4.0", "Returns the TTL in milliseconds of a hash field."),
+    CommandSpec::new("hpersist", -5, hpersist)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "7.4.0", "Removes the expiration time for each specified field"),
+    CommandSpec::new("hgetex", -5, hgetex)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "8.0.0", "Get the value of one or more fields of a given hash key, and optionally set their expiration."),
+    CommandSpec::new("hsetex", -6, hsetex)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("hash", "8.0.0", "Set the value of one or more fields of a given hash key, and optionally set their expiration."),
+];
+
+// the latest deadline a field can have, like redis' EB_EXPIRE_TIME_MAX
+const FIELD_EXPIRE_MAX: i64 = (1 << 48) - 1;
+
+fn empty() -> Value {
+    Value::Hash(Hash::default())
+}
+
+// like with lists, a hash goes away with its last field
+fn delete_if_empty(ctx: &mut Context, key: &Bytes) {
+    if let Ok(Some(hash)) = typed(ctx.db, key, Value::as_hash) {
+        if hash.is_empty() {
+            ctx.db.remove(key);
+        }
+    }
+}
+
+// a map in RESP3, a flat field value array in RESP2
+fn pairs_reply(pairs: Vec<(Bytes, Bytes)>) -> RespConcreteType {
+    RespConcreteType::Map(
+        pairs
+            .into_iter()
+            .map(|(field, value)| {
+                (
+                    RespConcreteType::BulkString(field),
+                    RespConcreteType::BulkString(value),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn hset(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    // the field value pairs come after the key
+    if argv.len() % 2 != 0 {
+        return Err(CommandError::WrongArity("hset".to_string()));
+    }
+    let hash = typed_or_create(ctx.db, &argv[1], Value::as_hash, empty)?;
+    let added = argv[2..]
+        .chunks(2)
+        .filter(|pair| hash.insert(pair[0].clone(), pair[1].clone()).is_none())
+        .count();
+    Ok(RespConcreteType::Int(added as i64))
+}
+
+fn hsetnx(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let hash = typed_or_create(ctx.db, &argv[1], Value::as_hash, empty)?;
+    if hash.get(&argv[2]).is_some() {
+        return Ok(RespConcreteType::Int(0));
+    }
+    hash.insert(argv[2].clone(), argv[3].clone());
+    Ok(RespConcreteType::Int(1))
+}
+
+fn hget(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let value = typed(ctx.db, &argv[1], Value::as_hash)?.and_then(|hash| hash.get(&argv[2]));
+    Ok(match value {
+        Some(value) => RespConcreteType::BulkString(value.clone()),
+        None => RespConcreteType::NullBulkString,
+    })
+}
+
+fn hmget(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let hash = typed(ctx.db, &argv[1], Value::as_hash)?;
+    let hash = hash.as_deref();
+    Ok(RespConcreteType::array(argv[2..].iter().map(|field| {
+        match hash.and_then(|hash| hash.get(field)) {
+            Some(value) => RespConcreteType::BulkString(value.clone()),
+            None => RespConcreteType::NullBulkString,
+        }
+    })))
+}
+
+fn hgetall(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let pairs = typed(ctx.db, &argv[1], Value::as_hash)?.map_or(vec![], |hash| {
+        hash.iter()
+            .map(|(field, value)| (field.clone(), value.clone()))
+            .collect()
+    });
+    Ok(pairs_reply(pairs))
+}
+
+fn hkeys(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let fields = typed(ctx.db, &argv[1], Value::as_hash)?.map_or(vec![], |hash| {
+        hash.iter().map(|(field, _)| field.clone()).collect()
+    });
+    Ok(RespConcreteType::array(
+        fields.into_iter().map(RespConcreteType::BulkString),
+    ))
+}
+
+fn hvals(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let values = typed(ctx.db, &argv[1], Value::as_hash)?.map_or(vec![], |hash| {
+        hash.iter().map(|(_, value)| value.clone()).collect()
+    });
+    Ok(RespConcreteType::array(
+        values.into_iter().map(RespConcreteType::BulkString),
+    ))
+}
+
+fn hlen(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let len = typed(ctx.db, &argv[1], Value::as_hash)?.map_or(0, |hash| hash.len());
+    Ok(RespConcreteType::Int(len as i64))
+}
+
+fn hexists(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let exists =
+        typed(ctx.db, &argv[1], Value::as_hash)?.is_some_and(|hash| hash.get(&argv[2]).is_some());
+    Ok(RespConcreteType::Int(exists as i64))
+}
+
+fn hdel(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let Some(hash) = typed(ctx.db, &argv[1], Value::as_hash)? else {
+        return Ok(RespConcreteType::Int(0));
+    };
+    let deleted = argv[2..]
+        .iter()
+        .filter(|field| hash.remove(field).is_some())
+        .count();
+    delete_if_empty(ctx, &argv[1]);
+    Ok(RespConcreteType::Int(deleted as i64))
+}
+
+fn hstrlen(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let len = typed(ctx.db, &argv[1], Value::as_hash)?
+        .and_then(|hash| hash.get(&argv[2]))
+        .map_or(0, |value| value.len());
+    Ok(RespConcreteType::Int(len as i64))
+}
+
+fn hincrby(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let increment = parse_int(&argv[3])?;
+    let hash = typed_or_create(ctx.db, &argv[1], Value::as_hash, empty)?;
+
+    let current = match hash.get(&argv[2]) {
+        Some(value) => parse_int(value).map_err(|_| CommandError::HashNotInteger)?,
+        None => 0,
+    };
+    let value = current
+        .checked_add(increment)
+        .ok_or(CommandError::Overflow)?;
+
+    hash.update(argv[2].clone(), value.to_string().into());
+    Ok(RespConcreteType::Int(value))
+}
+
+fn hincrbyfloat(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let increment = parse_float(&argv[3])?;
+    if increment.is_infinite() {
+        return Err(CommandError::ValueNanOrInfinity);
+    }
+    let hash = typed_or_create(ctx.db, &argv[1], Value::as_hash, empty)?;
+
+    let current = match hash.get(&argv[2]) {
+        Some(value) => parse_float(value).map_err(|_| CommandError::HashNotFloat)?,
+        None => 0.0,
+    };
+    let value = current + increment;
+    if !value.is_finite() {
+        return Err(CommandError::NanOrInfinity);
+    }
+
+    let value = format_float(value);
+    hash.update(argv[2].clone(), value.clone());
+    Ok(RespConcreteType::BulkString(value))
+}
+
+// a positive count picks distinct fields, a negative one may pick the same field again
+fn hrandfield(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let Some(count) = argv.get(2) else {
+        let field = typed(ctx.db, &argv[1], Value::as_hash)?
+            .and_then(|hash| hash.random().map(|(field, _)| field.clone()));
+        return Ok(match field {
+            Some(field) => RespConcreteType::BulkString(field),
+            None => RespConcreteType::NullBulkString,
+        });
+    };
+    let withvalues = match &argv[3..] {
+        [] => false,
+        [option] if option.eq_ignore_ascii_case(b"withvalues") => true,
+        _ => return Err(CommandError::Syntax),
+    };
+    // halved with values like redis, so that the count of replies can't overflow
+    let range = if withvalues { i64::MAX / 2 } else { i64::MAX };
+    let count = parse_int_in(count, -range, range)?;
+
+    let pairs: Vec<(Bytes, Bytes)> = match typed(ctx.db, &argv[1], Value::as_hash)? {
+        None => vec![],
+        Some(hash) if count < 0 => random::repeat(count.unsigned_abs() as usize, || {
+            let (field, value) = hash.random().expect("hashes are never empty");
+            (field.clone(), value.clone())
+        })
+        .ok_or(CommandError::CountTooLarge)?,
+        Some(hash) => random::pick(
+            hash.iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect(),
+            count as usize,
+        ),
+    };
+
+    Ok(match (withvalues, ctx.client.protocol) {
+        (false, _) => RespConcreteType::array(
+            pairs
+                .into_iter()
+                .map(|(field, _)| RespConcreteType::BulkString(field)),
+        ),
+        // RESP3 nests each pair, as the same field may come up more than once
+        (true, RespVersion::Resp3) => {
+            RespConcreteType::array(pairs.into_iter().map(|(field, value)| {
+                RespConcreteType::array([
+                    RespConcreteType::BulkString(field),
+                    RespConcreteType::BulkString(value),
+                ])
+            }))
+        }
+        (true, _) => RespConcreteType::array(pairs.into_iter().flat_map(|(field, value)| {
+            [
+                RespConcreteType::BulkString(field),
+                RespConcreteType::BulkString(value),
+            ]
+        })),
+    })
+}
+
+fn hscan(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let cursor = parse_cursor(&argv[2])?;
+
+    // NOVALUES is a flag among the MATCH and COUNT pairs
+    let (mut options, mut novalues) = (vec![], false);
+    let mut args = argv[3..].iter();
+    while let Some(arg) = args.next() {
+        if arg.eq_ignore_ascii_case(b"novalues") {
+            novalues = true;
+            continue;
+        }
+        options.push(arg.clone());
+        options.extend(args.next().cloned());
+    }
+    let options = scan_options(&options, false)?;
+
+    let Some(hash) = typed(ctx.db, &argv[1], Value::as_hash)? else {
+        return Ok(scan_reply(0, []));
+    };
+    let mut pairs = Vec::new();
+    let cursor = scan_walk(cursor, options.count, &mut pairs, |cursor, pairs| {
+        hash.scan(cursor, |field, value| {
+            pairs.push((field.clone(), value.clone()))
+        })
+    });
+    pairs.retain(|(field, _)| options.matches(field));
+
+    Ok(scan_reply(
+        cursor,
+        pairs.into_iter().flat_map(|(field, value)| {
+            let value = (!novalues).then_some(RespConcreteType::BulkString(value));
+            [RespConcreteType::BulkString(field)]
+                .into_iter()
+                .chain(value)
+        }),
+    ))
+}
+
+/// Splits `[options] FIELDS numfields ...` into the options and the arguments of
+/// the fields, `width` of them per field
+fn split_fields(args: &[Bytes], width: usize) -> Result<(&[Bytes], &[Bytes]), CommandError> {
+    let at = args
+        .iter()
+        .position(|arg| arg.eq_ignore_ascii_case(b"fields"))
+        .ok_or(CommandError::FieldsMissing)?;
+    let numfields = match args.get(at + 1).map(|numfields| parse_int(numfields)) {
+        Some(Ok(numfields)) if numfields > 0 => numfields as usize,
+        _ => return Err(CommandError::NumFieldsPositive),
+    };
+
+    let fields = &args[at + 2..];
+    if numfields.checked_mul(width) != Some(fields.len()) {
+        return Err(CommandError::NumFieldsMismatch);
+    }
+    Ok((&args[..at], fields))
+}
+
+fn ints(values: impl IntoIterator<Item = i64>) -> RespConcreteType {
+    RespConcreteType::array(values.into_iter().map(RespConcreteType::Int))
+}
+
+fn hexpire(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    hexpire_generic(ctx, argv, 1000, false)
+}
+
+fn hpexpire(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    hexpire_generic(ctx, argv, 1, false)
+}
+
+fn hexpireat(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    hexpire_generic(ctx, argv, 1000, true)
+}
+
+fn hpexpireat(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    hexpire_generic(ctx, argv, 1, true)
+}
+
+// per field: -2 when missing, 0 when the condition fails, 1 when set and 2 when deleted
+// by a deadline already in the past. `unit` is the number of milliseconds in one unit
+fn hexpire_generic(ctx: &mut Context, argv: &[Bytes], unit: i64, absolute: bool) -> CommandResult {
+    let invalid =
+        || CommandError::InvalidExpireTime(String::from_utf8_lossy(&argv[0]).to_lowercase());
+
+    let (options, fields) = split_fields(&argv[2..], 1)?;
+    let [time, condition @ ..] = options else {
+        return Err(CommandError::Syntax);
+    };
+    if condition.len() > 1 {
+        return Err(CommandError::Syntax);
+    }
+    let flags = expire_flags(condition)?;
+
+    let time = parse_int(time)?;
+    if time < 0 {
+        return Err(CommandError::NegativeExpireTime);
+    }
+    let now = ctx.db.now();
+    let when = time.checked_mul(unit).ok_or_else(invalid)?;
+    let when = match absolute {
+        true => when,
+        false => when.checked_add(now).ok_or_else(invalid)?,
+    };
+    if when > FIELD_EXPIRE_MAX {
+        return Err(invalid());
+    }
+
+    let key = &argv[1];
+    let Some(hash) = typed(ctx.db, key, Value::as_hash)? else {
+        return Ok(ints(fields.iter().map(|_| -2)));
+    };
+    let replies: Vec<i64> = fields
+        .iter()
+        .map(|field| {
+            if hash.get(field).is_none() {
+                -2
+            } else if !flags.applies(hash.expiry_at(field), when) {
+                0
+            } else if when <= now {
+                hash.remove(field);
+                2
+            } else {
+                hash.expire(field, Some(when));
+                1
+            }
+        })
+        .collect();
+
+    ctx.db.track_field_expiry(key);
+    delete_if_empty(ctx, key);
+    Ok(ints(replies))
+}
+
+fn httl(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    httl_generic(ctx, argv, |expiry_at, now| (expiry_at - now + 500) / 1000)
+}
+
+fn hpttl(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    httl_generic(ctx, argv, |expiry_at, now| expiry_at - now)
+}
+
+// per field: -2 when missing, -1 without a ttl, otherwise `reply(expiry_at, now)`
+fn httl_generic(ctx: &mut Context, argv: &[Bytes], reply: fn(i64, i64) -> i64) -> CommandResult {
+    let (options, fields) = split_fields(&argv[2..], 1)?;
+    if !options.is_empty() {
+        return Err(CommandError::Syntax);
+    }
+
+    let now = ctx.db.now();
+    let hash = typed(ctx.db, &argv[1], Value::as_hash)?;
+    let hash = hash.as_deref();
+    Ok(ints(fields.iter().map(
+        |field| match hash.filter(|hash| hash.get(field).is_some()) {
+            None => -2,
+            Some(hash) => match hash.expiry_at(field) {
+                None => -1,
+                Some(expiry_at) => reply(expiry_at, now.min(expiry_at)),
+            },
+        },
+    )))
+}
+
+// per field: -2 when missing, -1 without a ttl and 1 when the ttl was removed
+fn hpersist(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (options, fields) = split_fields(&argv[2..], 1)?;
+    if !options.is_empty() {
+        return Err(CommandError::Syntax);
+    }
+
+    let Some(hash) = typed(ctx.db, &argv[1], Value::as_hash)? else {
+        return Ok(ints(fields.iter().map(|_| -2)));
+    };
+    Ok(ints(fields.iter().map(
+        |field| match hash.get(field).is_some() {
+            false => -2,
+            true => match hash.expire(field, None) {
+                None => -1,
+                Some(_) => 1,
+            },
+        },
+    )))
+}
+
+enum FieldExpiry {
+    At(i64),
+    Persist,
+    KeepTtl,
+}
+
+// the EX, PX, EXAT or PXAT option of HGETEX and HSETEX
+fn field_expire_at(
+    ctx: &mut Context,
+    unit: &[u8],
+    time: Option<&Bytes>,
+    command: &str,
+) -> Result<FieldExpiry, CommandError> {
+    let time = parse_int(time.ok_or(CommandError::Syntax)?)?;
+    match expire_at(unit, time, ctx.db.now(), command)? {
+        expiry_at if expiry_at > FIELD_EXPIRE_MAX => {
+            Err(CommandError::InvalidExpireTime(command.to_string()))
+        }
+        expiry_at => Ok(FieldExpiry::At(expiry_at)),
+    }
+}
+
+// applies the expiry option of HGETEX or HSETEX to a field that exists
+fn expire_field(hash: &mut Hash, field: &Bytes, expiry: &Option<FieldExpiry>, now: i64) {
+    match expiry {
+        // a deadline in the past deletes the field
+        Some(FieldExpiry::At(expiry_at)) if *expiry_at <= now => {
+            hash.remove(field);
+        }
+        Some(FieldExpiry::At(expiry_at)) => {
+            hash.expire(field, Some(*expiry_at));
+        }
+        Some(FieldExpiry::Persist) => {
+            hash.expire(field, None);
+        }
+        Some(FieldExpiry::KeepTtl) | None => {}
+    }
+}
+
+fn hgetex(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (options, fields) = split_fields(&argv[2..], 1)?;
+
+    let mut expiry = None;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.to_ascii_lowercase().as_slice() {
+            b"persist" if expiry.is_none() => expiry = Some(FieldExpiry::Persist),
+            unit @ (b"ex" | b"px" | b"exat" | b"pxat") if expiry.is_none() => {
+                expiry = Some(field_expire_at(ctx, unit, options.next(), "hgetex")?);
+            }
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    let key = &argv[1];
+    let now = ctx.db.now();
+    let Some(hash) = typed(ctx.db, key, Value::as_hash)? else {
+        return Ok(RespConcreteType::array(
+            fields.iter().map(|_| RespConcreteType::NullBulkString),
+        ));
+    };
+    // the values are read before any field is deleted
+    let values: Vec<_> = fields
+        .iter()
+        .map(|field| match hash.get(field).cloned() {
+            Some(value) => {
+                expire_field(hash, field, &expiry, now);
+                RespConcreteType::BulkString(value)
+            }
+            None => RespConcreteType::NullBulkString,
+        })
+        .collect();
+
+    ctx.db.track_field_expiry(key);
+    delete_if_empty(ctx, key);
+    Ok(RespConcreteType::array(values))
+}
+
+// 1 when every field was set, 0 when FNX or FXX kept all of them from being set
+fn hsetex(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (options, pairs) = split_fields(&argv[2..], 2)?;
+
+    let (mut only_new, mut expiry) = (None, None);
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.to_ascii_lowercase().as_slice() {
+            b"fnx" if only_new.is_none() => only_new = Some(true),
+            b"fxx" if only_new.is_none() => only_new = Some(false),
+            b"keepttl" if expiry.is_none() => expiry = Some(FieldExpiry::KeepTtl),
+            unit @ (b"ex" | b"px" | b"exat" | b"pxat") if expiry.is_none() => {
+                expiry = Some(field_expire_at(ctx, unit, options.next(), "hsetex")?);
+            }
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    let key = &argv[1];
+    // FNX sets the fields only when none of them exist, FXX only when all of them do
+    let existing = typed(ctx.db, key, Value::as_hash)?.map_or(0, |hash| {
+        pairs
+            .chunks(2)
+            .filter(|pair| hash.get(&pair[0]).is_some())
+            .count()
+    });
+    match only_new {
+        Some(true) if existing > 0 => return Ok(RespConcreteType::Int(0)),
+        Some(false) if existing < pairs.len() / 2 => return Ok(RespConcreteType::Int(0)),
+        _ => {}
+    }
+
+    let now = ctx.db.now();
+    let hash = typed_or_create(ctx.db, key, Value::as_hash, empty)?;
+    for pair in pairs.chunks(2) {
+        let (field, value) = (pair[0].clone(), pair[1].clone());
+        match expiry {
+            Some(FieldExpiry::KeepTtl) => hash.update(field, value),
+            _ => hash.insert(field, value),
+        };
+        expire_field(hash, &pair[0], &expiry, now);
+    }
+
+    ctx.db.track_field_expiry(key);
+    delete_if_empty(ctx, key);
+    Ok(RespConcreteType::Int(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command::tests::Server;
+
+    #[tokio::test]
+    async fn hrandfield_halves_the_count_range_only_with_values() {
+        let mut server = Server::new();
+        assert_eq!(server.run(&["HSET", "h", "f", "v"]).await, ":1\r\n");
+        assert_eq!(
+            server
+                .run(&["HRANDFIELD", "h", "9223372036854775807"])
+                .await,
+            "*1\r\n$1\r\nf\r\n"
+        );
+        assert_eq!(
+            server
+                .run(&["HRANDFIELD", "h", "9223372036854775807", "WITHVALUES"])
+                .await,
+            "-ERR value is out of range, value must between -4611686018427387903 and 4611686018427387903\r\n"
+        );
+        assert_eq!(
+            server.run(&["HRANDFIELD", "h", "-2", "WITHVALUES"]).await,
+            "*4\r\n$1\r\nf\r\n$1\r\nv\r\n$1\r\nf\r\n$1\r\nv\r\n"
+        );
+        assert_eq!(
+            server.run(&["HRANDFIELD", "h", "x", "FOO"]).await,
+            "-ERR syntax error\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn hincrbyfloat_rejects_infinite_increments_up_front() {
+        let mut server = Server::new();
+        assert_eq!(server.run(&["SET", "s", "v"]).await, "+OK\r\n");
+        // refused before the key is looked at, so not a type error
+        assert_eq!(
+            server.run(&["HINCRBYFLOAT", "s", "f", "inf"]).await,
+            "-ERR value is NaN or Infinity\r\n"
+        );
+        assert_eq!(
+            server.run(&["HINCRBYFLOAT", "h", "f", "-inf"]).await,
+            "-ERR value is NaN or Infinity\r\n"
+        );
+        assert_eq!(server.run(&["EXISTS", "h"]).await, ":0\r\n");
+
+        assert_eq!(server.run(&["HSET", "h", "f", "1e308"]).await, ":1\r\n");
+        assert_eq!(
+            server.run(&["HINCRBYFLOAT", "h", "f", "1e308"]).await,
+            "-ERR increment would produce NaN or Infinity\r\n"
+        );
+    }
+}
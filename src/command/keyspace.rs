@@ -0,0 +1,330 @@
+use bytes::Bytes;
+
+use crate::glob;
+use crate::resp::RespConcreteType;
+
+use super::{parse_int, CommandError, CommandFlag::*, CommandResult, CommandSpec, Context};
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("del", -2, del)
+        .flags(&[Write])
+        .keys(1, -1, 1)
+        .docs("generic", "1.0.0", "Deletes one or more keys."),
+    CommandSpec::new("unlink", -2, unlink)
+        .flags(&[Write, Fast])
+        .keys(1, -1, 1)
+        .docs(
+            "generic",
+            "4.0.0",
+            "Asynchronously deletes one or more keys.",
+        ),
+    CommandSpec::new("exists", -2, exists)
+        .flags(&[Readonly, Fast])
+        .keys(1, -1, 1)
+        .docs(
+            "generic",
+            "1.0.0",
+            "Determines whether one or more keys exist.",
+        ),
+    CommandSpec::new("type", 2, type_)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "generic",
+            "1.0.0",
+            "Determines the type of value stored at a key.",
+        ),
+    CommandSpec::new("rename", 3, rename)
+        .flags(&[Write])
+        .keys(1, 2, 1)
+        .docs(
+            "generic",
+            "1.0.0",
+            "Renames a key and overwrites the destination.",
+        ),
+    CommandSpec::new("renamenx", 3, renamenx)
+        .flags(&[Write, Fast])
+        .keys(1, 2, 1)
+        .docs(
+            "generic",
+            "1.0.0",
+            "Renames a key only when the target key name doesn't exist.",
+        ),
+    CommandSpec::new("copy", -3, copy)
+        .flags(&[Write, Denyoom])
+        .keys(1, 2, 1)
+        .docs(
+            "generic",
+            "6.2.0",
+            "Copies the value of a key to a new key.",
+        ),
+    CommandSpec::new("keys", 2, keys).flags(&[Readonly]).docs(
+        "generic",
+        "1.0.0",
+        "Returns all key names that match a pattern.",
+    ),
+    CommandSpec::new("scan", -2, scan).flags(&[Readonly]).docs(
+        "generic",
+        "2.8.0",
+        "Iterates over the key names in the database.",
+    ),
+    CommandSpec::new("randomkey", 1, randomkey)
+        .flags(&[Readonly])
+        .docs(
+            "generic",
+            "1.0.0",
+            "Returns a random key name from the database.",
+        ),
+    CommandSpec::new("dbsize", 1, dbsize)
+        .flags(&[Readonly, Fast])
+        .docs(
+            "server",
+            "1.0.0",
+            "Returns the number of keys in the database.",
+        ),
+    CommandSpec::new("flushdb", -1, flushdb)
+        .flags(&[Write])
+        .docs(
+            "server",
+            "1.0.0",
+            "Removes all keys from the current database.",
+        ),
+    CommandSpec::new("flushall", -1, flushdb)
+        .flags(&[Write])
+        .docs("server", "1.0.0", "Removes all keys from all databases."),
+];
+
+fn del(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let deleted = argv[1..]
+        .iter()
+        .filter(|key| ctx.db.live(key).is_some() && ctx.db.remove(key).is_some())
+        .count();
+    Ok(RespConcreteType::Int(deleted as i64))
+}
+
+fn unlink(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let deleted = argv[1..]
+        .iter()
+        .filter(|key| ctx.db.live(key).is_some() && ctx.db.unlink(key))
+        .count();
+    Ok(RespConcreteType::Int(deleted as i64))
+}
+
+// a key given more than once is counted more than once
+fn exists(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let existing = argv[1..]
+        .iter()
+        .filter(|key| ctx.db.live(key).is_some())
+        .count();
+    Ok(RespConcreteType::Int(existing as i64))
+}
+
+fn type_(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let name = match ctx.db.live(&argv[1]) {
+        Some(entry) => entry.value.type_name(),
+        None => "none",
+    };
+    Ok(RespConcreteType::simple(name))
+}
+
+fn rename(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    rename_generic(ctx, argv, false)?;
+    Ok(RespConcreteType::ok())
+}
+
+fn renamenx(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let renamed = rename_generic(ctx, argv, true)?;
+    Ok(RespConcreteType::Int(renamed as i64))
+}
+
+// the value moves with its ttl, returns whether it moved
+fn rename_generic(ctx: &mut Context, argv: &[Bytes], nx: bool) -> Result<bool, CommandError> {
+    let (source, destination) = (&argv[1], &argv[2]);
+    if ctx.db.live(source).is_none() {
+        return Err(CommandError::NoSuchKey);
+    }
+    if source == destination {
+        return Ok(!nx);
+    }
+    if nx && ctx.db.live(destination).is_some() {
+        return Ok(false);
+    }
+
+    if let Some(value) = ctx.db.remove(source) {
+        ctx.db.unlink(destination);
+        ctx.db.insert(destination.clone(), value);
+    }
+    Ok(true)
+}
+
+fn copy(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (source, destination) = (&argv[1], &argv[2]);
+
+    let mut replace = false;
+    let mut options = argv[3..].iter();
+    while let Some(option) = options.next() {
+        match option.to_ascii_lowercase().as_slice() {
+            b"replace" => replace = true,
+            // there is only the one database
+            b"db" => {
+                let db = options.next().ok_or(CommandError::Syntax)?;
+                if parse_int(db)? != 0 {
+                    return Err(CommandError::DbIndexOutOfRange);
+                }
+            }
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    if source == destination {
+        return Err(CommandError::SameObject);
+    }
+    let Some(value) = ctx.db.live(source).cloned() else {
+        return Ok(RespConcreteType::Int(0));
+    };
+    if ctx.db.live(destination).is_some() {
+        if !replace {
+            return Ok(RespConcreteType::Int(0));
+        }
+        ctx.db.unlink(destination);
+    }
+
+    ctx.db.insert(destination.clone(), value);
+    Ok(RespConcreteType::Int(1))
+}
+
+fn keys(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let now = ctx.db.now();
+    Ok(RespConcreteType::array(
+        ctx.db
+            .iter()
+            .filter(|(key, value)| !value.is_expired(now) && glob::matches(&argv[1], key))
+            .map(|(key, _)| RespConcreteType::BulkString(key.clone())),
+    ))
+}
+
+/// The options shared by the SCAN family
+pub struct ScanOptions {
+    pub pattern: Option<Bytes>,
+    pub count: usize,
+    pub type_: Option<Bytes>,
+}
+
+impl ScanOptions {
+    pub fn matches(&self, element: &[u8]) -> bool {
+        self.pattern
+            .as_ref()
+            .map_or(true, |pattern| glob::matches(pattern, element))
+    }
+}
+
+pub fn parse_cursor(arg: &[u8]) -> Result<u64, CommandError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|cursor| cursor.parse().ok())
+        .ok_or(CommandError::InvalidCursor)
+}
+
+// `with_type` allows the TYPE option, which only SCAN itself has
+pub fn scan_options(args: &[Bytes], with_type: bool) -> Result<ScanOptions, CommandError> {
+    let mut options = ScanOptions {
+        pattern: None,
+        count: 10,
+        type_: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(option) = args.next() {
+        let value = args.next().ok_or(CommandError::Syntax)?;
+        match option.to_ascii_lowercase().as_slice() {
+            b"match" => options.pattern = Some(value.clone()),
+            b"count" => {
+                options.count = match parse_int(value)? {
+                    count if count < 1 => return Err(CommandError::Syntax),
+                    count => count as usize,
+                };
+            }
+            b"type" if with_type => options.type_ = Some(value.to_ascii_lowercase().into()),
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+    Ok(options)
+}
+
+/// Calls `step` bucket by bucket from `cursor` and returns the cursor to continue from.
+/// COUNT is a hint on the work done per call, not a page size, so this stops after
+/// about `count` elements or when too many buckets turned out empty
+pub fn scan_walk<T>(
+    mut cursor: u64,
+    count: usize,
+    elements: &mut Vec<T>,
+    mut step: impl FnMut(u64, &mut Vec<T>) -> u64,
+) -> u64 {
+    let mut steps = count.saturating_mul(10);
+    loop {
+        cursor = step(cursor, elements);
+        steps -= 1;
+        if cursor == 0 || steps == 0 || elements.len() >= count {
+            return cursor;
+        }
+    }
+}
+
+/// Builds the reply every SCAN style command sends, the next cursor and a page of elements
+pub fn scan_reply(
+    cursor: u64,
+    elements: impl IntoIterator<Item = RespConcreteType>,
+) -> RespConcreteType {
+    RespConcreteType::array([
+        RespConcreteType::bulk(cursor.to_string()),
+        RespConcreteType::array(elements),
+    ])
+}
+
+fn scan(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let cursor = parse_cursor(&argv[1])?;
+    let options = scan_options(&argv[2..], true)?;
+
+    let mut keys = Vec::new();
+    let cursor = scan_walk(cursor, options.count, &mut keys, |cursor, keys| {
+        ctx.db.scan(cursor, |key, _| keys.push(key.clone()))
+    });
+
+    // filters apply after the walk, expired keys met on the way are deleted
+    keys.retain(|key| {
+        options.matches(key)
+            && ctx.db.live(key).is_some_and(|entry| {
+                options
+                    .type_
+                    .as_ref()
+                    .map_or(true, |type_| entry.value.type_name().as_bytes() == type_)
+            })
+    });
+    Ok(scan_reply(
+        cursor,
+        keys.into_iter().map(RespConcreteType::BulkString),
+    ))
+}
+
+fn randomkey(ctx: &mut Context, _argv: &[Bytes]) -> CommandResult {
+    Ok(match ctx.db.random_key() {
+        Some(key) => RespConcreteType::BulkString(key),
+        None => RespConcreteType::NullBulkString,
+    })
+}
+
+fn dbsize(ctx: &mut Context, _argv: &[Bytes]) -> CommandResult {
+    Ok(RespConcreteType::Int(ctx.db.len() as i64))
+}
+
+// FLUSHALL is the same thing, there is only the one database
+fn flushdb(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let lazy = match argv.get(1).map(|option| option.to_ascii_lowercase()) {
+        None => false,
+        Some(option) if argv.len() == 2 && option == b"async" => true,
+        Some(option) if argv.len() == 2 && option == b"sync" => false,
+        Some(_) => return Err(CommandError::Syntax),
+    };
+    ctx.db.flush(lazy);
+    Ok(RespConcreteType::ok())
+}
@@ -0,0 +1,626 @@
+use bytes::Bytes;
+
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use crate::resp::RespConcreteType;
+use crate::storage::Value;
+
+use super::{
+    parse_float, parse_int, typed, typed_or_create, CommandError, CommandFlag::*, CommandResult,
+    CommandSpec, Context,
+};
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("lpush", -3, lpush)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("list", "1.0.0", "Prepends one or more elements to a list. Creates the key if it doesn't exist."),
+    CommandSpec::new("rpush", -3, rpush)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("list", "1.0.0", "Appends one or more elements to a list. Creates the key if it doesn't exist."),
+    CommandSpec::new("lpushx", -3, lpushx)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("list", "2.2.0", "Prepends one or more elements to a list only when the list exists."),
+    CommandSpec::new("rpushx", -3, rpushx)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("list", "2.2.0", "Appends an element to a list only when the list exists."),
+    CommandSpec::new("lpop", -2, lpop)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs("list", "1.0.0", "Returns the first elements in a list after removing it. Deletes the list if the last element was popped."),
+    CommandSpec::new("rpop", -2, rpop)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs("list", "1.0.0", "Returns and removes the last elements of a list. Deletes the list if the last element was popped."),
+    CommandSpec::new("llen", 2, llen)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("list", "1.0.0", "Returns the length of a list."),
+    CommandSpec::new("lrange", 4, lrange)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs("list", "1.0.0", "Returns a range of elements from a list."),
+    CommandSpec::new("lindex", 3, lindex)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs("list", "1.0.0", "Returns an element from a list by its index."),
+    CommandSpec::new("lset", 4, lset)
+        .flags(&[Write, Denyoom])
+        .keys(1, 1, 1)
+        .docs("list", "1.0.0", "Sets the value of an element in a list by its index."),
+    CommandSpec::new("linsert", 5, linsert)
+        .flags(&[Write, Denyoom])
+        .keys(1, 1, 1)
+        .docs("list", "2.2.0", "Inserts an element before or after another element in a list."),
+    CommandSpec::new("lrem", 4, lrem)
+        .flags(&[Write])
+        .keys(1, 1, 1)
+        .docs("list", "1.0.0", "Removes elements from a list. Deletes the list if the last element was removed."),
+    CommandSpec::new("ltrim", 4, ltrim)
+        .flags(&[Write])
+        .keys(1, 1, 1)
+        .docs("list", "1.0.0", "Removes elements from both ends a list. Deletes the list if all elements were trimmed."),
+    CommandSpec::new("lpos", -3, lpos)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs("list", "6.0.6", "Returns the index of matching elements in a list."),
+    CommandSpec::new("lmove", 5, lmove)
+        .flags(&[Write, Denyoom])
+        .keys(1, 2, 1)
+        .docs("list", "6.2.0", "Returns an element after popping it from one list and pushing it to another. Deletes the list if the last element was moved."),
+    CommandSpec::new("lmpop", -4, lmpop)
+        .flags(&[Write])
+        .numkeys(1)
+        .docs("list", "7.0.0", "Returns multiple elements from a list after removing them. Deletes the list if the last element was popped."),
+    CommandSpec::new("blpop", -3, blpop)
+        .flags(&[Write, Blocking])
+        .keys(1, -2, 1)
+        .docs("list", "2.0.0", "Removes and returns the first element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped."),
+    CommandSpec::new("brpop", -3, brpop)
+        .flags(&[Write, Blocking])
+        .keys(1, -2, 1)
+        .docs("list", "2.0.0", "Removes and returns the last element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped."),
+    CommandSpec::new("blmove", 6, blmove)
+        .flags(&[Write, Denyoom, Blocking])
+        .keys(1, 2, 1)
+        .docs("list", "6.2.0", "Pops an element from a list, pushes it to another list and returns it. Blocks until an element is available otherwise. Deletes the list if the last element was moved."),
+    CommandSpec::new("blmpop", -5, blmpop)
+        .flags(&[Write, Blocking])
+        .numkeys(2)
+        .docs("list", "7.0.0", "Pops the first element from one of multiple lists. Blocks until an element is available otherwise. Deletes the list if the last element was popped."),
+];
+
+#[derive(Clone, Copy)]
+pub enum End {
+    Left,
+    Right,
+}
+
+impl End {
+    pub fn parse(arg: &[u8]) -> Result<End, CommandError> {
+        match arg.to_ascii_lowercase().as_slice() {
+            b"left" => Ok(End::Left),
+            b"right" => Ok(End::Right),
+            _ => Err(CommandError::Syntax),
+        }
+    }
+}
+
+fn empty() -> Value {
+    Value::List(VecDeque::new())
+}
+
+/// Pushes elements one by one, so pushing `a b c` to the left ends with `c b a`
+pub fn push(list: &mut VecDeque<Bytes>, end: End, elements: impl IntoIterator<Item = Bytes>) {
+    for element in elements {
+        match end {
+            End::Left => list.push_front(element),
+            End::Right => list.push_back(element),
+        }
+    }
+}
+
+/// Pops up to `count` elements from the list at `key`, deleting the key once it is empty
+pub fn pop(
+    ctx: &mut Context,
+    key: &Bytes,
+    end: End,
+    count: usize,
+) -> Result<Option<Vec<Bytes>>, CommandError> {
+    let Some(list) = typed(ctx.db, key, Value::as_list)? else {
+        return Ok(None);
+    };
+    let count = count.min(list.len());
+    let popped = match end {
+        End::Left => list.drain(..count).collect(),
+        End::Right => list.drain(list.len() - count..).rev().collect(),
+    };
+    delete_if_empty(ctx, key);
+    Ok(Some(popped))
+}
+
+// a list never stays around empty, the key goes with its last element
+fn delete_if_empty(ctx: &mut Context, key: &Bytes) {
+    if let Ok(Some(list)) = typed(ctx.db, key, Value::as_list) {
+        if list.is_empty() {
+            ctx.db.remove(key);
+        }
+    }
+}
+
+/// Resolves a `start stop` pair with negative indexes counting from the end,
+/// `None` when the range is empty
+fn range(start: i64, stop: i64, len: usize) -> Option<RangeInclusive<usize>> {
+    let len = len as i64;
+    let start = match start < 0 {
+        true => (len + start).max(0),
+        false => start,
+    };
+    let stop = match stop < 0 {
+        true => len + stop,
+        false => stop.min(len - 1),
+    };
+    match start > stop || start >= len {
+        true => None,
+        false => Some(start as usize..=stop as usize),
+    }
+}
+
+// a single index, negative ones counting from the end
+fn index(index: i64, len: usize) -> Option<usize> {
+    let index = match index < 0 {
+        true => len as i64 + index,
+        false => index,
+    };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+fn lpush(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    push_generic(ctx, argv, End::Left, true)
+}
+
+fn rpush(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    push_generic(ctx, argv, End::Right, true)
+}
+
+fn lpushx(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    push_generic(ctx, argv, End::Left, false)
+}
+
+fn rpushx(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    push_generic(ctx, argv, End::Right, false)
+}
+
+fn push_generic(ctx: &mut Context, argv: &[Bytes], end: End, create: bool) -> CommandResult {
+    let key = &argv[1];
+    let list = match create {
+        true => typed_or_create(ctx.db, key, Value::as_list, empty)?,
+        false => match typed(ctx.db, key, Value::as_list)? {
+            Some(list) => list,
+            None => return Ok(RespConcreteType::Int(0)),
+        },
+    };
+    push(list, end, argv[2..].iter().cloned());
+    Ok(RespConcreteType::Int(list.len() as i64))
+}
+
+fn lpop(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    pop_generic(ctx, argv, End::Left)
+}
+
+fn rpop(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    pop_generic(ctx, argv, End::Right)
+}
+
+// without a count a single element is returned, with one an array of them
+fn pop_generic(ctx: &mut Context, argv: &[Bytes], end: End) -> CommandResult {
+    if argv.len() > 3 {
+        return Err(CommandError::WrongArity(
+            String::from_utf8_lossy(&argv[0]).to_lowercase(),
+        ));
+    }
+    let count = match argv.get(2) {
+        Some(count) => match parse_int(count) {
+            Ok(count) if count >= 0 => Some(count as usize),
+            _ => return Err(CommandError::MustBePositive),
+        },
+        None => None,
+    };
+
+    let popped = pop(ctx, &argv[1], end, count.unwrap_or(1))?;
+    Ok(match (popped, count) {
+        (None, None) => RespConcreteType::NullBulkString,
+        (None, Some(_)) => RespConcreteType::NullArray,
+        (Some(popped), None) => match popped.into_iter().next() {
+            Some(element) => RespConcreteType::BulkString(element),
+            None => RespConcreteType::NullBulkString,
+        },
+        (Some(popped), Some(_)) => {
+            RespConcreteType::array(popped.into_iter().map(RespConcreteType::BulkString))
+        }
+    })
+}
+
+fn llen(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let len = typed(ctx.db, &argv[1], Value::as_list)?.map_or(0, |list| list.len());
+    Ok(RespConcreteType::Int(len as i64))
+}
+
+fn lrange(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (start, stop) = (parse_int(&argv[2])?, parse_int(&argv[3])?);
+    let Some(list) = typed(ctx.db, &argv[1], Value::as_list)? else {
+        return Ok(RespConcreteType::array([]));
+    };
+    let elements = match range(start, stop, list.len()) {
+        Some(range) => list.range(range).cloned().collect(),
+        None => vec![],
+    };
+    Ok(RespConcreteType::array(
+        elements.into_iter().map(RespConcreteType::BulkString),
+    ))
+}
+
+fn lindex(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let i = parse_int(&argv[2])?;
+    let element = typed(ctx.db, &argv[1], Value::as_list)?
+        .and_then(|list| index(i, list.len()).map(|i| list[i].clone()));
+    Ok(match element {
+        Some(element) => RespConcreteType::BulkString(element),
+        None => RespConcreteType::NullBulkString,
+    })
+}
+
+fn lset(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let i = parse_int(&argv[2])?;
+    let list = typed(ctx.db, &argv[1], Value::as_list)?.ok_or(CommandError::NoSuchKey)?;
+    let i = index(i, list.len()).ok_or(CommandError::IndexOutOfRange)?;
+    list[i] = argv[3].clone();
+    Ok(RespConcreteType::ok())
+}
+
+fn linsert(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let after = match argv[2].to_ascii_lowercase().as_slice() {
+        b"before" => false,
+        b"after" => true,
+        _ => return Err(CommandError::Syntax),
+    };
+    let (pivot, element) = (&argv[3], &argv[4]);
+
+    let Some(list) = typed(ctx.db, &argv[1], Value::as_list)? else {
+        return Ok(RespConcreteType::Int(0));
+    };
+    let Some(i) = list.iter().position(|candidate| candidate == pivot) else {
+        return Ok(RespConcreteType::Int(-1));
+    };
+    list.insert(i + after as usize, element.clone());
+    Ok(RespConcreteType::Int(list.len() as i64))
+}
+
+// a positive count removes from the head, a negative one from the tail, zero removes all
+fn lrem(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let count = parse_int(&argv[2])?;
+    let element = &argv[3];
+    let Some(list) = typed(ctx.db, &argv[1], Value::as_list)? else {
+        return Ok(RespConcreteType::Int(0));
+    };
+
+    let limit = match count {
+        0 => usize::MAX,
+        count => count.unsigned_abs() as usize,
+    };
+    // a negative count removes the last matches, so the ones before them are kept
+    let total = list.iter().filter(|&e| e == element).count();
+    let removed = total.min(limit);
+    let kept = match count < 0 {
+        true => total - removed,
+        false => 0,
+    };
+    let mut seen = 0;
+    list.retain(|e| {
+        if e != element {
+            return true;
+        }
+        seen += 1;
+        seen <= kept || seen > kept + removed
+    });
+
+    delete_if_empty(ctx, &argv[1]);
+    Ok(RespConcreteType::Int(removed as i64))
+}
+
+fn ltrim(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (start, stop) = (parse_int(&argv[2])?, parse_int(&argv[3])?);
+    let Some(list) = typed(ctx.db, &argv[1], Value::as_list)? else {
+        return Ok(RespConcreteType::ok());
+    };
+    match range(start, stop, list.len()) {
+        Some(range) => {
+            list.truncate(range.end() + 1);
+            list.drain(..range.start());
+        }
+        None => list.clear(),
+    }
+    delete_if_empty(ctx, &argv[1]);
+    Ok(RespConcreteType::ok())
+}
+
+fn lpos(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let element = &argv[2];
+    let (mut rank, mut count, mut maxlen) = (1, None, 0);
+
+    let mut options = argv[3..].iter();
+    while let Some(option) = options.next() {
+        // the option is recognised before its value is parsed
+        let mut value = || parse_int(options.next().ok_or(CommandError::Syntax)?);
+        match option.to_ascii_lowercase().as_slice() {
+            b"rank" => {
+                rank = match value()? {
+                    0 => return Err(CommandError::RankZero),
+                    i64::MIN => return Err(CommandError::NotInteger),
+                    rank => rank,
+                }
+            }
+            b"count" => {
+                count = match value()? {
+                    count if count < 0 => return Err(CommandError::CountNegative),
+                    count => Some(count as usize),
+                }
+            }
+            b"maxlen" => {
+                maxlen = match value()? {
+                    maxlen if maxlen < 0 => return Err(CommandError::MaxlenNegative),
+                    maxlen => maxlen as usize,
+                }
+            }
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    let matches: Vec<usize> = match typed(ctx.db, &argv[1], Value::as_list)? {
+        None => vec![],
+        Some(list) => {
+            // a negative rank searches from the tail, MAXLEN bounds the comparisons
+            let compared = match maxlen {
+                0 => list.len(),
+                maxlen => maxlen.min(list.len()),
+            };
+            let indexes: Box<dyn Iterator<Item = usize>> = match rank > 0 {
+                true => Box::new(0..compared),
+                false => Box::new((list.len() - compared..list.len()).rev()),
+            };
+            indexes
+                .filter(|&i| list[i] == element)
+                .skip(rank.unsigned_abs() as usize - 1)
+                .take(match count {
+                    None => 1,
+                    Some(0) => usize::MAX,
+                    Some(count) => count,
+                })
+                .collect()
+        }
+    };
+
+    Ok(match count {
+        Some(_) => {
+            RespConcreteType::array(matches.into_iter().map(|i| RespConcreteType::Int(i as i64)))
+        }
+        None => match matches.first() {
+            Some(&i) => RespConcreteType::Int(i as i64),
+            None => RespConcreteType::NullBulkString,
+        },
+    })
+}
+
+fn lmove(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (from, to) = (End::parse(&argv[3])?, End::parse(&argv[4])?);
+    Ok(match move_element(ctx, &argv[1], &argv[2], from, to)? {
+        Some(element) => RespConcreteType::BulkString(element),
+        None => RespConcreteType::NullBulkString,
+    })
+}
+
+/// Pops an element off `source` and pushes it to `destination`, `None` when there is
+/// nothing to move. The destination's type is checked before anything is popped
+pub fn move_element(
+    ctx: &mut Context,
+    source: &Bytes,
+    destination: &Bytes,
+    from: End,
+    to: End,
+) -> Result<Option<Bytes>, CommandError> {
+    if typed(ctx.db, source, Value::as_list)?.is_none() {
+        return Ok(None);
+    }
+    typed(ctx.db, destination, Value::as_list)?;
+
+    let list = typed(ctx.db, source, Value::as_list)?.expect("checked above");
+    let element = match from {
+        End::Left => list.pop_front(),
+        End::Right => list.pop_back(),
+    }
+    .expect("lists are never empty");
+    // pushed before an emptied source is deleted, so a list rotated onto itself stays
+    // the same key and keeps its ttl
+    let list = typed_or_create(ctx.db, destination, Value::as_list, empty)?;
+    push(list, to, [element.clone()]);
+    delete_if_empty(ctx, source);
+    Ok(Some(element))
+}
+
+/// The `numkeys key [key ...] LEFT|RIGHT [COUNT count]` arguments of LMPOP and BLMPOP
+pub fn mpop_args(args: &[Bytes]) -> Result<(&[Bytes], End, usize), CommandError> {
+    let numkeys = match parse_int(&args[0]) {
+        Ok(numkeys) if numkeys > 0 => numkeys as usize,
+        _ => return Err(CommandError::NumKeys),
+    };
+    let keys = args.get(1..=numkeys).ok_or(CommandError::Syntax)?;
+    let end = End::parse(args.get(numkeys + 1).ok_or(CommandError::Syntax)?)?;
+
+    let count = match &args[numkeys + 2..] {
+        [] => 1,
+        [option, count] if option.eq_ignore_ascii_case(b"count") => match parse_int(count) {
+            Ok(count) if count > 0 => count as usize,
+            _ => return Err(CommandError::CountPositive),
+        },
+        _ => return Err(CommandError::Syntax),
+    };
+    Ok((keys, end, count))
+}
+
+/// Pops from the first of `keys` holding a non empty list, replying with its name and the
+/// popped elements, `None` when every list is empty
+pub fn mpop(
+    ctx: &mut Context,
+    keys: &[Bytes],
+    end: End,
+    count: usize,
+) -> Result<Option<RespConcreteType>, CommandError> {
+    for key in keys {
+        if let Some(popped) = pop(ctx, key, end, count)? {
+            return Ok(Some(RespConcreteType::array([
+                RespConcreteType::BulkString(key.clone()),
+                RespConcreteType::array(popped.into_iter().map(RespConcreteType::BulkString)),
+            ])));
+        }
+    }
+    Ok(None)
+}
+
+fn lmpop(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (keys, end, count) = mpop_args(&argv[1..])?;
+    Ok(mpop(ctx, keys, end, count)?.unwrap_or(RespConcreteType::NullArray))
+}
+
+/// A timeout in seconds, sub-second ones included. Zero blocks forever
+fn parse_timeout(arg: &[u8]) -> Result<Option<Duration>, CommandError> {
+    let timeout = parse_float(arg).map_err(|_| CommandError::TimeoutNotFloat)?;
+    if timeout < 0.0 {
+        return Err(CommandError::TimeoutNegative);
+    }
+    match timeout == 0.0 {
+        true => Ok(None),
+        false => Duration::try_from_secs_f64(timeout)
+            .map(Some)
+            .map_err(|_| CommandError::TimeoutOutOfRange),
+    }
+}
+
+fn blpop(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    bpop_generic(ctx, argv, End::Left)
+}
+
+fn brpop(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    bpop_generic(ctx, argv, End::Right)
+}
+
+// replies with the key popped from and the element, the first non empty list wins
+fn bpop_generic(ctx: &mut Context, argv: &[Bytes], end: End) -> CommandResult {
+    let (keys, timeout) = argv[1..].split_at(argv.len() - 2);
+    let timeout = parse_timeout(&timeout[0])?;
+
+    for key in keys {
+        if let Some(popped) = pop(ctx, key, end, 1)? {
+            return Ok(RespConcreteType::array(
+                [key.clone()]
+                    .into_iter()
+                    .chain(popped)
+                    .map(RespConcreteType::BulkString),
+            ));
+        }
+    }
+    ctx.block(keys, timeout);
+    Ok(RespConcreteType::NullArray)
+}
+
+fn blmove(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (from, to) = (End::parse(&argv[3])?, End::parse(&argv[4])?);
+    let timeout = parse_timeout(&argv[5])?;
+    match move_element(ctx, &argv[1], &argv[2], from, to)? {
+        Some(element) => Ok(RespConcreteType::BulkString(element)),
+        None => {
+            ctx.block(&argv[1..2], timeout);
+            Ok(RespConcreteType::NullBulkString)
+        }
+    }
+}
+
+fn blmpop(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let timeout = parse_timeout(&argv[1])?;
+    let (keys, end, count) = mpop_args(&argv[2..])?;
+    match mpop(ctx, keys, end, count)? {
+        Some(reply) => Ok(reply),
+        None => {
+            ctx.block(keys, timeout);
+            Ok(RespConcreteType::NullArray)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command::tests::Server;
+
+    #[tokio::test]
+    async fn pop_with_extra_arguments_is_an_arity_error() {
+        let mut server = Server::new();
+        assert_eq!(server.run(&["RPUSH", "l", "a", "b"]).await, ":2\r\n");
+        assert_eq!(
+            server.run(&["LPOP", "l", "1", "2"]).await,
+            "-ERR wrong number of arguments for 'lpop' command\r\n"
+        );
+        assert_eq!(server.run(&["RPOP", "l", "1"]).await, "*1\r\n$1\r\nb\r\n");
+    }
+
+    #[tokio::test]
+    async fn lpos_checks_the_option_before_its_value() {
+        let mut server = Server::new();
+        assert_eq!(server.run(&["RPUSH", "l", "a", "b", "a"]).await, ":3\r\n");
+        assert_eq!(
+            server.run(&["LPOS", "l", "a", "FOO", "x"]).await,
+            "-ERR syntax error\r\n"
+        );
+        assert_eq!(
+            server.run(&["LPOS", "l", "a", "RANK", "x"]).await,
+            "-ERR value is not an integer or out of range\r\n"
+        );
+        assert_eq!(
+            server.run(&["LPOS", "l", "a", "RANK"]).await,
+            "-ERR syntax error\r\n"
+        );
+        assert_eq!(
+            server
+                .run(&["LPOS", "l", "a", "RANK", "-1", "COUNT", "0"])
+                .await,
+            "*2\r\n:2\r\n:0\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn lrem_removes_from_either_end() {
+        let mut server = Server::new();
+        let elements = ["RPUSH", "l", "a", "b", "a", "c", "a"];
+        assert_eq!(server.run(&elements).await, ":5\r\n");
+        assert_eq!(server.run(&["LREM", "l", "-2", "a"]).await, ":2\r\n");
+        assert_eq!(
+            server.run(&["LRANGE", "l", "0", "-1"]).await,
+            "*3\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n"
+        );
+
+        assert_eq!(server.run(&elements).await, ":8\r\n");
+        assert_eq!(server.run(&["LREM", "l", "2", "a"]).await, ":2\r\n");
+        assert_eq!(
+            server.run(&["LRANGE", "l", "0", "-1"]).await,
+            "*6\r\n$1\r\nb\r\n$1\r\nc\r\n$1\r\nb\r\n$1\r\na\r\n$1\r\nc\r\n$1\r\na\r\n"
+        );
+
+        assert_eq!(server.run(&["LREM", "l", "0", "a"]).await, ":2\r\n");
+        assert_eq!(server.run(&["LREM", "l", "0", "b"]).await, ":2\r\n");
+        assert_eq!(server.run(&["LREM", "l", "0", "c"]).await, ":2\r\n");
+        assert_eq!(server.run(&["EXISTS", "l"]).await, ":0\r\n");
+    }
+}
@@ -0,0 +1,648 @@
+use bytes::Bytes;
+
+use tokio::sync::oneshot;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::blocking::Waiter;
+use crate::config::Config;
+use crate::resp::{RespConcreteType, RespVersion};
+use crate::storage::{Db, Storage, Value};
+
+pub use self::error::CommandError;
+
+pub mod bitmap;
+pub mod connection;
+pub mod error;
+pub mod expire;
+pub mod hash;
+pub mod keyspace;
+pub mod list;
+pub mod server;
+pub mod set;
+pub mod string;
+
+pub type CommandResult = Result<RespConcreteType, CommandError>;
+
+/// Handlers run with the keyspace locked and receive the full argv, command name included
+pub type Handler = fn(&mut Context, &[Bytes]) -> CommandResult;
+
+/// Per connection state
+#[derive(Debug)]
+pub struct Client {
+    pub id: u64,
+    pub protocol: RespVersion,
+    // set once the reply to the current command is the last one
+    pub closing: bool,
+}
+
+impl Client {
+    pub fn new(id: u64) -> Client {
+        Client {
+            id,
+            // every connection starts in RESP2 until it negotiates otherwise with HELLO
+            protocol: RespVersion::Resp2,
+            closing: false,
+        }
+    }
+}
+
+pub struct Context<'a> {
+    pub db: &'a mut Db,
+    pub client: &'a mut Client,
+    pub config: &'a Config,
+    block: Option<Block>,
+}
+
+impl Context<'_> {
+    /// Blocks the client on `keys` once the handler returns, forever without a `timeout`.
+    /// The command runs again when one of them is pushed to, the handler's reply is what
+    /// the client gets if that doesn't happen in time
+    pub fn block(&mut self, keys: &[Bytes], timeout: Option<Duration>) {
+        self.block = Some(Block {
+            keys: keys.to_vec(),
+            timeout,
+        });
+    }
+}
+
+struct Block {
+    keys: Vec<Bytes>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandFlag {
+    Write,
+    Readonly,
+    Denyoom,
+    Admin,
+    Pubsub,
+    Noscript,
+    Blocking,
+    Loading,
+    Stale,
+    Fast,
+    NoAuth,
+}
+
+impl CommandFlag {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CommandFlag::Write => "write",
+            CommandFlag::Readonly => "readonly",
+            CommandFlag::Denyoom => "denyoom",
+            CommandFlag::Admin => "admin",
+            CommandFlag::Pubsub => "pubsub",
+            CommandFlag::Noscript => "noscript",
+            CommandFlag::Blocking => "blocking",
+            CommandFlag::Loading => "loading",
+            CommandFlag::Stale => "stale",
+            CommandFlag::Fast => "fast",
+            CommandFlag::NoAuth => "no_auth",
+        }
+    }
+}
+
+/// An entry of the command table, mirroring what redis reports through `COMMAND INFO`
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub handler: Handler,
+    // positive arities are exact, negative ones are a minimum, both count the command name
+    pub arity: i64,
+    pub flags: &'static [CommandFlag],
+    pub first_key: i64,
+    pub last_key: i64,
+    pub key_step: i64,
+    // for commands like LMPOP, the position of the argument counting the keys that follow it
+    pub numkeys: Option<usize>,
+    pub group: &'static str,
+    pub since: &'static str,
+    pub summary: &'static str,
+}
+
+impl CommandSpec {
+    pub const fn new(name: &'static str, arity: i64, handler: Handler) -> CommandSpec {
+        CommandSpec {
+            name,
+            handler,
+            arity,
+            flags: &[],
+            first_key: 0,
+            last_key: 0,
+            key_step: 0,
+            numkeys: None,
+            group: "generic",
+            since: "1.0.0",
+            summary: "",
+        }
+    }
+
+    pub const fn flags(mut self, flags: &'static [CommandFlag]) -> CommandSpec {
+        self.flags = flags;
+        self
+    }
+
+    pub const fn keys(mut self, first_key: i64, last_key: i64, key_step: i64) -> CommandSpec {
+        self.first_key = first_key;
+        self.last_key = last_key;
+        self.key_step = key_step;
+        self
+    }
+
+    pub const fn numkeys(mut self, index: usize) -> CommandSpec {
+        self.numkeys = Some(index);
+        self
+    }
+
+    pub const fn docs(
+        mut self,
+        group: &'static str,
+        since: &'static str,
+        summary: &'static str,
+    ) -> CommandSpec {
+        self.group = group;
+        self.since = since;
+        self.summary = summary;
+        self
+    }
+
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    pub fn check_arity(&self, argc: usize) -> bool {
+        match self.arity >= 0 {
+            true => argc as i64 == self.arity,
+            false => argc as i64 >= -self.arity,
+        }
+    }
+
+    /// Positions of the keys in argv
+    pub fn key_positions(&self, argv: &[Bytes]) -> Vec<usize> {
+        if let Some(index) = self.numkeys {
+            let numkeys = argv
+                .get(index)
+                .and_then(|numkeys| parse_int(numkeys).ok())
+                .unwrap_or(0)
+                .max(0) as usize;
+            return (index + 1..argv.len()).take(numkeys).collect();
+        }
+        if self.first_key <= 0 {
+            return vec![];
+        }
+
+        let last_key = match self.last_key < 0 {
+            true => argv.len() as i64 + self.last_key,
+            false => self.last_key.min(argv.len() as i64 - 1),
+        };
+        (self.first_key..=last_key)
+            .step_by(self.key_step.max(1) as usize)
+            .map(|i| i as usize)
+            .collect()
+    }
+}
+
+pub fn commands() -> impl Iterator<Item = &'static CommandSpec> {
+    [
+        bitmap::COMMANDS,
+        connection::COMMANDS,
+        expire::COMMANDS,
+        hash::COMMANDS,
+        keyspace::COMMANDS,
+        list::COMMANDS,
+        server::COMMANDS,
+        set::COMMANDS,
+        string::COMMANDS,
+    ]
+    .into_iter()
+    .flatten()
+}
+
+pub fn lookup(name: &[u8]) -> Option<&'static CommandSpec> {
+    static TABLE: OnceLock<HashMap<&'static [u8], &'static CommandSpec>> = OnceLock::new();
+
+    TABLE
+        .get_or_init(|| {
+            commands()
+                .map(|spec| (spec.name.as_bytes(), spec))
+                .collect()
+        })
+        .get(name.to_ascii_lowercase().as_slice())
+        .copied()
+}
+
+/// Runs a request against the keyspace, errors are turned into error replies. A command that
+/// blocks only returns once it is served or times out
+pub async fn execute(
+    argv: Vec<Bytes>,
+    storage: &Storage,
+    config: &Config,
+    client: &mut Client,
+) -> RespConcreteType {
+    let spec = match lookup(&argv[0]) {
+        None => {
+            let error = CommandError::UnknownCommand(argv[0].clone(), argv[1..].to_vec());
+            return error_reply(error);
+        }
+        Some(spec) if !spec.check_arity(argv.len()) => {
+            return error_reply(CommandError::WrongArity(spec.name.to_string()));
+        }
+        Some(spec) => spec,
+    };
+
+    let mut db = storage.write().await;
+    let mut ctx = Context {
+        db: &mut db,
+        client,
+        config,
+        block: None,
+    };
+    let reply = (spec.handler)(&mut ctx, &argv);
+    let block = ctx.block.take();
+
+    if spec.has_flag(CommandFlag::Write) && !db.blocked.is_empty() {
+        let touched = spec.key_positions(&argv).into_iter();
+        serve_blocked(&mut db, config, touched.map(|i| argv[i].clone()).collect());
+    }
+
+    let reply = reply.unwrap_or_else(error_reply);
+    match block {
+        None => reply,
+        Some(block) => {
+            let (tx, mut rx) = oneshot::channel();
+            let waiter = Waiter::new(client.protocol, argv, block.keys, tx);
+            db.blocked.block(client.id, waiter);
+            drop(db);
+            let guard = BlockGuard {
+                storage,
+                client_id: client.id,
+                waiting: true,
+            };
+
+            let served = match block.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, &mut rx).await.ok(),
+                None => Some((&mut rx).await),
+            };
+            let reply = match served {
+                Some(Ok(served)) => served,
+                // timed out, unless served while the lock was being taken back
+                _ => {
+                    storage.write().await.blocked.unblock(client.id);
+                    rx.try_recv().unwrap_or(reply)
+                }
+            };
+            guard.disarm();
+            reply
+        }
+    }
+}
+
+/// Unblocks a client whose blocking command is dropped before it is served or times out, as
+/// when the client disconnects or overflows its query buffer while waiting. Nothing else takes
+/// it off the queues of keys that no one pushes to
+struct BlockGuard<'a> {
+    storage: &'a Storage,
+    client_id: u64,
+    waiting: bool,
+}
+
+impl BlockGuard<'_> {
+    fn disarm(mut self) {
+        self.waiting = false;
+    }
+}
+
+impl Drop for BlockGuard<'_> {
+    fn drop(&mut self) {
+        if !self.waiting {
+            return;
+        }
+        let client_id = self.client_id;
+        match self.storage.try_write() {
+            Ok(mut db) => {
+                db.blocked.unblock(client_id);
+            }
+            // drop cannot wait for the lock, the client is gone so later is as good
+            Err(_) => {
+                let storage = self.storage.clone();
+                tokio::spawn(async move {
+                    storage.write().await.blocked.unblock(client_id);
+                });
+            }
+        }
+    }
+}
+
+fn error_reply(error: CommandError) -> RespConcreteType {
+    RespConcreteType::Error(error.to_string())
+}
+
+/// Serves the clients blocked on the `ready` keys in the order they blocked, right after the
+/// command that pushed, like redis' `handleClientsBlockedOnKeys`. A served command can push to
+/// another key in turn (BLMOVE), so the keys it touched are served next
+fn serve_blocked(db: &mut Db, config: &Config, mut ready: VecDeque<Bytes>) {
+    while let Some(key) = ready.pop_front() {
+        while let Some((id, waiter)) = db.blocked.first(&key) {
+            if waiter.is_gone() {
+                db.blocked.unblock(id);
+                continue;
+            }
+            let mut client = Client::new(id);
+            client.protocol = waiter.protocol;
+            let argv = waiter.argv.clone();
+
+            // a key deleted or replaced by another type keeps its clients blocked, and once a
+            // waiter consumed the list the ones behind it stay blocked too
+            if !db
+                .live(&key)
+                .is_some_and(|entry| matches!(entry.value, Value::List(_)))
+            {
+                break;
+            }
+
+            let spec = lookup(&argv[0]).expect("only known commands block");
+            let mut ctx = Context {
+                db,
+                client: &mut client,
+                config,
+                block: None,
+            };
+            let reply = (spec.handler)(&mut ctx, &argv);
+            if ctx.block.is_some() {
+                break;
+            }
+
+            ready.extend(
+                spec.key_positions(&argv)
+                    .into_iter()
+                    .map(|i| argv[i].clone()),
+            );
+            if let Some(waiter) = db.blocked.unblock(id) {
+                waiter.reply(reply.unwrap_or_else(error_reply));
+            }
+        }
+    }
+}
+
+/// Looks up a key holding the kind of value `kind` accepts, any other kind is WRONGTYPE
+pub fn typed<'a, T>(
+    db: &'a mut Db,
+    key: &Bytes,
+    kind: fn(&mut Value) -> Option<&mut T>,
+) -> Result<Option<&'a mut T>, CommandError> {
+    match db.live(key) {
+        Some(entry) => kind(&mut entry.value)
+            .map(Some)
+            .ok_or(CommandError::WrongType),
+        None => Ok(None),
+    }
+}
+
+/// Like `typed`, but a missing key is created with `empty` first
+pub fn typed_or_create<'a, T>(
+    db: &'a mut Db,
+    key: &Bytes,
+    kind: fn(&mut Value) -> Option<&mut T>,
+    empty: fn() -> Value,
+) -> Result<&'a mut T, CommandError> {
+    kind(&mut db.get_or_insert(key, empty).value).ok_or(CommandError::WrongType)
+}
+
+// like redis, only the canonical form is an integer: no sign but '-', no leading zeros or spaces
+pub fn parse_int(arg: &[u8]) -> Result<i64, CommandError> {
+    let digits = arg.strip_prefix(b"-").unwrap_or(arg);
+    let canonical = match digits {
+        [b'0'] => arg.len() == 1,
+        [b'1'..=b'9', rest @ ..] => rest.iter().all(u8::is_ascii_digit),
+        _ => false,
+    };
+    if !canonical {
+        return Err(CommandError::NotInteger);
+    }
+
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|arg| arg.parse::<i64>().ok())
+        .ok_or(CommandError::NotInteger)
+}
+
+/// Like `parse_int`, but out of range values are refused the way redis'
+/// `getRangeLongFromObjectOrReply` does
+pub fn parse_int_in(arg: &[u8], min: i64, max: i64) -> Result<i64, CommandError> {
+    match parse_int(arg)? {
+        value if (min..=max).contains(&value) => Ok(value),
+        _ => Err(CommandError::ValueOutOfRange(min, max)),
+    }
+}
+
+// NaN is never a number, infinities are fine as long as they don't end up stored
+pub fn parse_float(arg: &[u8]) -> Result<f64, CommandError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|float| float.parse::<f64>().ok())
+        .filter(|float| !float.is_nan())
+        .ok_or(CommandError::NotFloat)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+    use tokio::sync::RwLock;
+
+    use std::future::{poll_fn, Future};
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::Poll;
+    use std::time::{Duration, Instant};
+
+    use super::{execute, Client};
+    use crate::clock::ManualClock;
+    use crate::config::Config;
+    use crate::resp::{encode::encode, RespConcreteType, RespVersion};
+    use crate::storage::{Db, Storage};
+
+    const START_MS: i64 = 1_700_000_000_000;
+
+    // a keyspace on a clock that only moves when the test says so
+    pub(super) struct Server {
+        storage: Storage,
+        clock: Arc<ManualClock>,
+        config: Config,
+        client: Client,
+    }
+
+    impl Server {
+        pub(super) fn new() -> Server {
+            let clock = Arc::new(ManualClock::new(START_MS));
+            Server {
+                storage: Arc::new(RwLock::new(Db::new(clock.clone()))),
+                clock,
+                config: Config::default(),
+                client: Client::new(1),
+            }
+        }
+
+        // the reply to a command, as RESP2 text
+        pub(super) async fn run(&mut self, args: &[&str]) -> String {
+            let reply = execute(argv(args), &self.storage, &self.config, &mut self.client).await;
+            resp2(&reply)
+        }
+
+        // the same from another client, whose command can be left waiting
+        async fn run_as(&self, client: &mut Client, args: &[&str]) -> String {
+            let reply = execute(argv(args), &self.storage, &self.config, client).await;
+            resp2(&reply)
+        }
+
+        pub(super) fn advance(&self, ms: u64) {
+            self.clock.advance(Duration::from_millis(ms));
+        }
+    }
+
+    fn argv(args: &[&str]) -> Vec<Bytes> {
+        args.iter()
+            .map(|arg| Bytes::from(arg.to_string()))
+            .collect()
+    }
+
+    fn resp2(reply: &RespConcreteType) -> String {
+        let mut out = BytesMut::new();
+        encode(reply, RespVersion::Resp2, &mut out);
+        String::from_utf8(out.to_vec()).unwrap()
+    }
+
+    // polls a command once, as the connection loop does before it waits on a blocked one
+    async fn poll_once<F: Future + Unpin>(command: &mut F) -> Poll<F::Output> {
+        poll_fn(|cx| Poll::Ready(Pin::new(&mut *command).poll(cx))).await
+    }
+
+    #[tokio::test]
+    async fn expire_runs_out_with_the_clock() {
+        let mut server = Server::new();
+        assert_eq!(server.run(&["SET", "k", "v"]).await, "+OK\r\n");
+        assert_eq!(server.run(&["EXPIRE", "k", "10"]).await, ":1\r\n");
+        assert_eq!(server.run(&["PTTL", "k"]).await, ":10000\r\n");
+
+        server.advance(9_999);
+        assert_eq!(server.run(&["PTTL", "k"]).await, ":1\r\n");
+        // the deadline itself is the last moment the key is there
+        server.advance(1);
+        assert_eq!(server.run(&["GET", "k"]).await, "$1\r\nv\r\n");
+        server.advance(1);
+        assert_eq!(server.run(&["GET", "k"]).await, "$-1\r\n");
+        assert_eq!(server.run(&["TTL", "k"]).await, ":-2\r\n");
+        assert_eq!(server.storage.read().await.stats.expired_keys, 1);
+    }
+
+    #[tokio::test]
+    async fn pxat_deadlines_are_absolute() {
+        let mut server = Server::new();
+        let deadline = (START_MS + 1_500).to_string();
+        assert_eq!(
+            server.run(&["SET", "k", "v", "PXAT", &deadline]).await,
+            "+OK\r\n"
+        );
+        assert_eq!(server.run(&["PTTL", "k"]).await, ":1500\r\n");
+        assert_eq!(
+            server.run(&["PEXPIRETIME", "k"]).await,
+            format!(":{deadline}\r\n")
+        );
+
+        server.clock.set(START_MS + 1_000);
+        assert_eq!(server.run(&["PERSIST", "k"]).await, ":1\r\n");
+        server.clock.set(START_MS + 60_000);
+        assert_eq!(server.run(&["GET", "k"]).await, "$1\r\nv\r\n");
+
+        // a deadline already behind the clock leaves nothing to read
+        let past = (START_MS + 59_999).to_string();
+        assert_eq!(
+            server.run(&["SET", "k", "v", "PXAT", &past]).await,
+            "+OK\r\n"
+        );
+        assert_eq!(server.run(&["EXISTS", "k"]).await, ":0\r\n");
+    }
+
+    #[tokio::test]
+    async fn hexpire_expires_fields_then_the_hash() {
+        let mut server = Server::new();
+        assert_eq!(
+            server.run(&["HSET", "h", "a", "1", "b", "2"]).await,
+            ":2\r\n"
+        );
+        assert_eq!(
+            server.run(&["HEXPIRE", "h", "5", "FIELDS", "1", "a"]).await,
+            "*1\r\n:1\r\n"
+        );
+        assert_eq!(
+            server.run(&["HPTTL", "h", "FIELDS", "2", "a", "b"]).await,
+            "*2\r\n:5000\r\n:-1\r\n"
+        );
+
+        server.advance(5_001);
+        assert_eq!(server.run(&["HGET", "h", "a"]).await, "$-1\r\n");
+        assert_eq!(server.run(&["HLEN", "h"]).await, ":1\r\n");
+
+        // the hash goes with its last field
+        assert_eq!(
+            server
+                .run(&["HPEXPIRE", "h", "100", "FIELDS", "1", "b"])
+                .await,
+            "*1\r\n:1\r\n"
+        );
+        server.advance(101);
+        assert_eq!(server.run(&["EXISTS", "h"]).await, ":0\r\n");
+        assert_eq!(server.storage.read().await.stats.expired_subkeys, 2);
+    }
+
+    #[tokio::test]
+    async fn blocking_pop_times_out() {
+        let mut server = Server::new();
+        let started = Instant::now();
+        assert_eq!(server.run(&["BLPOP", "l", "0.05"]).await, "*-1\r\n");
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(server.storage.read().await.blocked.is_empty());
+    }
+
+    #[tokio::test]
+    async fn blocked_clients_are_served_in_the_order_they_blocked() {
+        let server = Server::new();
+        let (mut first, mut second) = (Client::new(2), Client::new(3));
+        let mut first_pop = Box::pin(server.run_as(&mut first, &["BLPOP", "l", "0"]));
+        let mut second_pop = Box::pin(server.run_as(&mut second, &["BLPOP", "l", "0"]));
+        assert!(poll_once(&mut first_pop).await.is_pending());
+        assert!(poll_once(&mut second_pop).await.is_pending());
+
+        let mut pusher = Client::new(4);
+        assert_eq!(
+            server.run_as(&mut pusher, &["RPUSH", "l", "a"]).await,
+            ":1\r\n"
+        );
+        assert!(poll_once(&mut second_pop).await.is_pending());
+        assert_eq!(first_pop.await, "*2\r\n$1\r\nl\r\n$1\r\na\r\n");
+
+        assert_eq!(
+            server.run_as(&mut pusher, &["RPUSH", "l", "b"]).await,
+            ":1\r\n"
+        );
+        assert_eq!(second_pop.await, "*2\r\n$1\r\nl\r\n$1\r\nb\r\n");
+        assert!(server.storage.read().await.blocked.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_dropped_blocking_command_stops_waiting() {
+        let mut server = Server::new();
+        let mut other = Client::new(2);
+        let mut pop = Box::pin(server.run_as(&mut other, &["BLPOP", "l", "0"]));
+        assert!(poll_once(&mut pop).await.is_pending());
+        assert!(!server.storage.read().await.blocked.is_empty());
+
+        // what the connection does when the client hangs up while blocked
+        drop(pop);
+        assert!(server.storage.read().await.blocked.is_empty());
+        assert_eq!(server.run(&["RPUSH", "l", "a"]).await, ":1\r\n");
+        assert_eq!(server.run(&["LLEN", "l"]).await, ":1\r\n");
+    }
+}
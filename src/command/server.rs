@@ -0,0 +1,345 @@
+use bytes::Bytes;
+
+use std::fmt::Write;
+
+use crate::resp::{string::RespVerbatimStringConcrete, RespConcreteType};
+
+use super::{
+    commands, connection::VERSION, lookup, CommandError, CommandFlag, CommandResult, CommandSpec,
+    Context,
+};
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("command", -1, command)
+        .flags(&[CommandFlag::Loading, CommandFlag::Stale])
+        .docs(
+            "server",
+            "2.8.13",
+            "Returns detailed information about all commands.",
+        ),
+    CommandSpec::new("info", -1, info)
+        .flags(&[CommandFlag::Loading, CommandFlag::Stale])
+        .docs(
+            "server",
+            "1.0.0",
+            "Returns information and statistics about the server.",
+        ),
+];
+
+const INFO_SECTIONS: &[&str] = &["server", "stats", "keyspace"];
+
+fn info(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let requested: Vec<String> = argv[1..]
+        .iter()
+        .map(|section| String::from_utf8_lossy(section).to_lowercase())
+        .collect();
+    let everything = requested.is_empty()
+        || requested
+            .iter()
+            .any(|section| matches!(section.as_str(), "all" | "default" | "everything"));
+
+    let mut sections = Vec::new();
+    for &section in INFO_SECTIONS {
+        if !everything && !requested.iter().any(|requested| requested == section) {
+            continue;
+        }
+
+        let mut text = String::new();
+        match section {
+            "server" => {
+                text.push_str("# Server\r\n");
+                let _ = write!(
+                    text,
+                    "redis_version:{VERSION}\r\nredis_mode:standalone\r\nprocess_id:{}\r\ntcp_port:{}\r\n",
+                    std::process::id(),
+                    crate::PORT
+                );
+            }
+            "stats" => {
+                let stats = &ctx.db.stats;
+                text.push_str("# Stats\r\n");
+                let _ = write!(
+                    text,
+                    "expired_keys:{}\r\nexpired_subkeys:{}\r\nexpired_stale_perc:{:.2}\r\nexpired_time_cap_reached_count:{}\r\nexpire_cycle_cpu_milliseconds:{}\r\n",
+                    stats.expired_keys,
+                    stats.expired_subkeys,
+                    stats.expired_stale_perc * 100.0,
+                    stats.expired_time_cap_reached_count,
+                    stats.expire_cycle_cpu_milliseconds
+                );
+            }
+            _ => {
+                text.push_str("# Keyspace\r\n");
+                if ctx.db.len() > 0 {
+                    let _ = write!(
+                        text,
+                        "db0:keys={},expires={},avg_ttl=0\r\n",
+                        ctx.db.len(),
+                        ctx.db.expires()
+                    );
+                }
+            }
+        }
+        sections.push(text);
+    }
+
+    Ok(RespConcreteType::VerbatimString(
+        RespVerbatimStringConcrete {
+            format: "txt".to_string(),
+            string: sections.join("\r\n").into(),
+        },
+    ))
+}
+
+fn command(_ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let Some(subcommand) = argv.get(1) else {
+        return Ok(RespConcreteType::array(commands().map(command_info)));
+    };
+
+    match subcommand.to_ascii_lowercase().as_slice() {
+        b"count" if argv.len() == 2 => Ok(RespConcreteType::Int(commands().count() as i64)),
+        b"list" if argv.len() == 2 => Ok(RespConcreteType::array(
+            commands().map(|spec| RespConcreteType::bulk(spec.name)),
+        )),
+        b"info" => Ok(RespConcreteType::array(specs(&argv[2..]).map(
+            |spec| match spec {
+                Some(spec) => command_info(spec),
+                None => RespConcreteType::NullArray,
+            },
+        ))),
+        b"docs" => Ok(RespConcreteType::Map(
+            specs(&argv[2..])
+                .flatten()
+                .map(|spec| (RespConcreteType::bulk(spec.name), command_docs(spec)))
+                .collect(),
+        )),
+        b"getkeys" if argv.len() >= 3 => {
+            let argv = &argv[2..];
+            let spec = lookup(&argv[0]).ok_or(CommandError::InvalidCommandSpecified)?;
+            if !spec.check_arity(argv.len()) {
+                return Err(CommandError::InvalidArgumentCount);
+            }
+
+            let keys = spec.key_positions(argv);
+            if keys.is_empty() {
+                return Err(CommandError::NoKeyArguments);
+            }
+            Ok(RespConcreteType::array(
+                keys.into_iter()
+                    .map(|i| RespConcreteType::BulkString(argv[i].clone())),
+            ))
+        }
+        b"help" if argv.len() == 2 => Ok(RespConcreteType::array(
+            [
+                "COMMAND <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+                "(no subcommand)",
+                "    Return details about all Redis commands.",
+                "COUNT",
+                "    Return the total number of commands in this Redis server.",
+                "LIST",
+                "    Return a list of all commands in this Redis server.",
+                "INFO [<command-name> ...]",
+                "    Return details about multiple Redis commands.",
+                "    If no command names are given, documentation details for all",
+                "    commands are returned.",
+                "DOCS [<command-name> ...]",
+                "    Return documentation details about multiple Redis commands.",
+                "    If no command names are given, documentation details for all",
+                "    commands are returned.",
+                "GETKEYS <full-command>",
+                "    Return the keys from a full Redis command.",
+                "HELP",
+                "    Print this help.",
+            ]
+            .map(RespConcreteType::simple),
+        )),
+        _ => Err(CommandError::UnknownSubcommand(
+            "command".to_string(),
+            subcommand.clone(),
+        )),
+    }
+}
+
+// every command when no names are given, otherwise a lookup per name
+fn specs(names: &[Bytes]) -> Box<dyn Iterator<Item = Option<&'static CommandSpec>> + '_> {
+    match names.is_empty() {
+        true => Box::new(commands().map(Some)),
+        false => Box::new(names.iter().map(|name| lookup(name))),
+    }
+}
+
+fn command_info(spec: &CommandSpec) -> RespConcreteType {
+    RespConcreteType::array([
+        RespConcreteType::bulk(spec.name),
+        RespConcreteType::Int(spec.arity),
+        RespConcreteType::Set(
+            spec.flags
+                .iter()
+                .map(|flag| flag.name())
+                // keys found through a numkeys argument can't be described by first/last/step
+                .chain(spec.numkeys.map(|_| "movablekeys"))
+                .map(RespConcreteType::simple)
+                .collect(),
+        ),
+        RespConcreteType::Int(spec.first_key),
+        RespConcreteType::Int(spec.last_key),
+        RespConcreteType::Int(spec.key_step),
+        RespConcreteType::Set(
+            acl_categories(spec)
+                .into_iter()
+                .map(RespConcreteType::simple)
+                .collect(),
+        ),
+        // tips
+        RespConcreteType::array([]),
+        key_specs(spec),
+        // subcommands
+        RespConcreteType::array([]),
+    ])
+}
+
+fn acl_categories(spec: &CommandSpec) -> Vec<&'static str> {
+    let mut categories = vec![match spec.group {
+        "generic" => "@keyspace",
+        "string" => "@string",
+        "list" => "@list",
+        "hash" => "@hash",
+        "set" => "@set",
+        "sorted-set" => "@sortedset",
+        "bitmap" => "@bitmap",
+        "connection" => "@connection",
+        _ => "@server",
+    }];
+
+    if spec.has_flag(CommandFlag::Write) {
+        categories.push("@write");
+    }
+    if spec.has_flag(CommandFlag::Readonly) {
+        categories.push("@read");
+    }
+    categories.push(match spec.has_flag(CommandFlag::Fast) {
+        true => "@fast",
+        false => "@slow",
+    });
+    if spec.has_flag(CommandFlag::Blocking) {
+        categories.push("@blocking");
+    }
+    if spec.has_flag(CommandFlag::Admin) {
+        categories.extend(["@admin", "@dangerous"]);
+    }
+    if spec.has_flag(CommandFlag::Pubsub) {
+        categories.push("@pubsub");
+    }
+    categories
+}
+
+// key specs are derived from the legacy first/last/step triple
+fn key_specs(spec: &CommandSpec) -> RespConcreteType {
+    if spec.first_key <= 0 && spec.numkeys.is_none() {
+        return RespConcreteType::array([]);
+    }
+
+    let access = match spec.has_flag(CommandFlag::Write) {
+        true => ["RW", "UPDATE"],
+        false => ["RO", "ACCESS"],
+    };
+    let (begin, find_keys) = match spec.numkeys {
+        Some(index) => (
+            index as i64,
+            map([
+                ("type", RespConcreteType::bulk("keynum")),
+                (
+                    "spec",
+                    map([
+                        ("keynumidx", RespConcreteType::Int(0)),
+                        ("firstkey", RespConcreteType::Int(1)),
+                        ("keystep", RespConcreteType::Int(1)),
+                    ]),
+                ),
+            ]),
+        ),
+        None => (spec.first_key, range_keys(spec)),
+    };
+
+    RespConcreteType::array([map([
+        (
+            "flags",
+            RespConcreteType::Set(access.into_iter().map(RespConcreteType::simple).collect()),
+        ),
+        (
+            "begin_search",
+            map([
+                ("type", RespConcreteType::bulk("index")),
+                ("spec", map([("index", RespConcreteType::Int(begin))])),
+            ]),
+        ),
+        ("find_keys", find_keys),
+    ])])
+}
+
+fn range_keys(spec: &CommandSpec) -> RespConcreteType {
+    map([
+        ("type", RespConcreteType::bulk("range")),
+        (
+            "spec",
+            map([
+                (
+                    "lastkey",
+                    RespConcreteType::Int(match spec.last_key < 0 {
+                        true => spec.last_key,
+                        false => spec.last_key - spec.first_key,
+                    }),
+                ),
+                ("keystep", RespConcreteType::Int(spec.key_step)),
+                ("limit", RespConcreteType::Int(0)),
+            ]),
+        ),
+    ])
+}
+
+fn command_docs(spec: &CommandSpec) -> RespConcreteType {
+    map([
+        ("summary", RespConcreteType::bulk(spec.summary)),
+        ("since", RespConcreteType::bulk(spec.since)),
+        ("group", RespConcreteType::bulk(spec.group)),
+    ])
+}
+
+fn map<const N: usize>(entries: [(&'static str, RespConcreteType); N]) -> RespConcreteType {
+    RespConcreteType::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (RespConcreteType::bulk(key), value))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command::tests::Server;
+
+    #[tokio::test]
+    async fn getkeys_follows_the_key_specs() {
+        let mut server = Server::new();
+        assert_eq!(
+            server.run(&["COMMAND", "GETKEYS", "LCS", "a", "b"]).await,
+            "*2\r\n$1\r\na\r\n$1\r\nb\r\n"
+        );
+        assert_eq!(
+            server
+                .run(&["COMMAND", "GETKEYS", "LCS", "a", "b", "LEN"])
+                .await,
+            "*2\r\n$1\r\na\r\n$1\r\nb\r\n"
+        );
+        assert_eq!(
+            server
+                .run(&["COMMAND", "GETKEYS", "MSET", "a", "1", "b", "2"])
+                .await,
+            "*2\r\n$1\r\na\r\n$1\r\nb\r\n"
+        );
+        assert_eq!(
+            server.run(&["COMMAND", "GETKEYS", "PING"]).await,
+            "-ERR The command has no key arguments\r\n"
+        );
+    }
+}
@@ -0,0 +1,454 @@
+use bytes::Bytes;
+
+use crate::dict::Dict;
+use crate::random;
+use crate::resp::RespConcreteType;
+use crate::storage::{StorageValue, Value};
+
+use super::keyspace::{parse_cursor, scan_options, scan_reply, scan_walk};
+use super::{
+    parse_int, parse_int_in, typed, typed_or_create, CommandError, CommandFlag::*, CommandResult,
+    CommandSpec, Context,
+};
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("sadd", -3, sadd)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs("set", "1.0.0", "Adds one or more members to a set. Creates the key if it doesn't exist."),
+    CommandSpec::new("srem", -3, srem)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs("set", "1.0.0", "Removes one or more members from a set. Deletes the set if the last member was removed."),
+    CommandSpec::new("smembers", 2, smembers)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs("set", "1.0.0", "Returns all members of a set."),
+    CommandSpec::new("sismember", 3, sismember)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("set", "1.0.0", "Determines whether a member belongs to a set."),
+    CommandSpec::new("smismember", -3, smismember)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("set", "6.2.0", "Determines whether multiple members belong to a set."),
+    CommandSpec::new("scard", 2, scard)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("set", "1.0.0", "Returns the number of members in a set."),
+    CommandSpec::new("spop", -2, spop)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs("set", "1.0.0", "Returns one or more random members from a set after removing them. Deletes the set if the last member was popped."),
+    CommandSpec::new("srandmember", -2, srandmember)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs("set", "1.0.0", "Get one or multiple random members from a set"),
+    CommandSpec::new("smove", 4, smove)
+        .flags(&[Write, Fast])
+        .keys(1, 2, 1)
+        .docs("set", "1.0.0", "Moves a member from one set to another."),
+    CommandSpec::new("sinter", -2, sinter)
+        .flags(&[Readonly])
+        .keys(1, -1, 1)
+        .docs("set", "1.0.0", "Returns the intersect of multiple sets."),
+    CommandSpec::new("sinterstore", -3, sinterstore)
+        .flags(&[Write, Denyoom])
+        .keys(1, -1, 1)
+        .docs("set", "1.0.0", "Stores the intersect of multiple sets in a key."),
+    CommandSpec::new("sintercard", -3, sintercard)
+        .flags(&[Readonly])
+        .numkeys(1)
+        .docs("set", "7.0.0", "Returns the number of members of the intersect of multiple sets."),
+    CommandSpec::new("sunion", -2, sunion)
+        .flags(&[Readonly])
+        .keys(1, -1, 1)
+        .docs("set", "1.0.0", "Returns the union of multiple sets."),
+    CommandSpec::new("sunionstore", -3, sunionstore)
+        .flags(&[Write, Denyoom])
+        .keys(1, -1, 1)
+        .docs("set", "1.0.0", "Stores the union of multiple sets in a key."),
+    CommandSpec::new("sdiff", -2, sdiff)
+        .flags(&[Readonly])
+        .keys(1, -1, 1)
+        .docs("set", "1.0.0", "Returns the difference of multiple sets."),
+    CommandSpec::new("sdiffstore", -3, sdiffstore)
+        .flags(&[Write, Denyoom])
+        .keys(1, -1, 1)
+        .docs("set", "1.0.0", "Stores the difference of multiple sets in a key."),
+    CommandSpec::new("sscan", -3, sscan)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs("set", "2.0.0", "Iterates over members of a set."),
+];
+
+type Set = Dict<Bytes, ()>;
+
+fn empty() -> Value {
+    Value::Set(Set::default())
+}
+
+// like lists and hashes, a set goes away with its last member
+fn delete_if_empty(ctx: &mut Context, key: &Bytes) {
+    if let Ok(Some(set)) = typed(ctx.db, key, Value::as_set) {
+        if set.is_empty() {
+            ctx.db.remove(key);
+        }
+    }
+}
+
+fn members_reply(members: impl IntoIterator<Item = Bytes>) -> RespConcreteType {
+    RespConcreteType::Set(
+        members
+            .into_iter()
+            .map(RespConcreteType::BulkString)
+            .collect(),
+    )
+}
+
+fn sadd(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let set = typed_or_create(ctx.db, &argv[1], Value::as_set, empty)?;
+    let added = argv[2..]
+        .iter()
+        .filter(|member| set.insert((*member).clone(), ()).is_none())
+        .count();
+    Ok(RespConcreteType::Int(added as i64))
+}
+
+fn srem(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let Some(set) = typed(ctx.db, &argv[1], Value::as_set)? else {
+        return Ok(RespConcreteType::Int(0));
+    };
+    let removed = argv[2..]
+        .iter()
+        .filter(|member| set.remove(member).is_some())
+        .count();
+    delete_if_empty(ctx, &argv[1]);
+    Ok(RespConcreteType::Int(removed as i64))
+}
+
+fn smembers(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let members: Vec<Bytes> = typed(ctx.db, &argv[1], Value::as_set)?.map_or(vec![], |set| {
+        set.iter().map(|(member, _)| member.clone()).collect()
+    });
+    Ok(members_reply(members))
+}
+
+fn sismember(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let member =
+        typed(ctx.db, &argv[1], Value::as_set)?.is_some_and(|set| set.get(&argv[2]).is_some());
+    Ok(RespConcreteType::Int(member as i64))
+}
+
+fn smismember(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let set = typed(ctx.db, &argv[1], Value::as_set)?;
+    let set = set.as_deref();
+    Ok(RespConcreteType::array(argv[2..].iter().map(|member| {
+        let member = set.is_some_and(|set| set.get(member).is_some());
+        RespConcreteType::Int(member as i64)
+    })))
+}
+
+fn scard(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let len = typed(ctx.db, &argv[1], Value::as_set)?.map_or(0, |set| set.len());
+    Ok(RespConcreteType::Int(len as i64))
+}
+
+fn spop(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let key = &argv[1];
+    let count = match &argv[2..] {
+        [] => None,
+        [count] => match parse_int(count) {
+            Ok(count) if count >= 0 => Some(count as usize),
+            _ => return Err(CommandError::MustBePositive),
+        },
+        _ => return Err(CommandError::Syntax),
+    };
+
+    let Some(set) = typed(ctx.db, key, Value::as_set)? else {
+        return Ok(match count {
+            None => RespConcreteType::NullBulkString,
+            Some(_) => members_reply([]),
+        });
+    };
+    let popped = match count {
+        None => set
+            .random()
+            .map(|(member, _)| member.clone())
+            .into_iter()
+            .collect(),
+        Some(count) => random::pick(
+            set.iter().map(|(member, _)| member.clone()).collect(),
+            count,
+        ),
+    };
+    for member in &popped {
+        set.remove(member);
+    }
+    delete_if_empty(ctx, key);
+
+    Ok(match count {
+        None => {
+            RespConcreteType::BulkString(popped.into_iter().next().expect("sets are never empty"))
+        }
+        Some(_) => members_reply(popped),
+    })
+}
+
+// a positive count picks distinct members, a negative one may pick the same member again
+fn srandmember(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let count = match &argv[2..] {
+        [] => None,
+        [count] => Some(parse_int_in(count, -i64::MAX, i64::MAX)?),
+        _ => return Err(CommandError::Syntax),
+    };
+
+    let set = typed(ctx.db, &argv[1], Value::as_set)?;
+    let Some(count) = count else {
+        return Ok(match set.and_then(|set| set.random()) {
+            Some((member, _)) => RespConcreteType::BulkString(member.clone()),
+            None => RespConcreteType::NullBulkString,
+        });
+    };
+    let members: Vec<Bytes> = match set {
+        None => vec![],
+        Some(set) if count < 0 => random::repeat(count.unsigned_abs() as usize, || {
+            let (member, _) = set.random().expect("sets are never empty");
+            member.clone()
+        })
+        .ok_or(CommandError::CountTooLarge)?,
+        Some(set) => random::pick(
+            set.iter().map(|(member, _)| member.clone()).collect(),
+            count as usize,
+        ),
+    };
+    Ok(RespConcreteType::array(
+        members.into_iter().map(RespConcreteType::BulkString),
+    ))
+}
+
+fn smove(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (source, destination, member) = (&argv[1], &argv[2], &argv[3]);
+    if typed(ctx.db, source, Value::as_set)?.is_none() {
+        return Ok(RespConcreteType::Int(0));
+    }
+    typed(ctx.db, destination, Value::as_set)?;
+
+    let set = typed(ctx.db, source, Value::as_set)?.expect("checked above");
+    // moving within the same set changes nothing
+    if source == destination {
+        return Ok(RespConcreteType::Int(set.get(member).is_some() as i64));
+    }
+    if set.remove(member).is_none() {
+        return Ok(RespConcreteType::Int(0));
+    }
+    delete_if_empty(ctx, source);
+
+    typed_or_create(ctx.db, destination, Value::as_set, empty)?.insert(member.clone(), ());
+    Ok(RespConcreteType::Int(1))
+}
+
+/// The sets at `keys`, `None` for the missing ones. Every key is checked to hold a set
+/// and expired first, then they are all read at once
+fn operands<'a>(
+    ctx: &'a mut Context,
+    keys: &[Bytes],
+) -> Result<Vec<Option<&'a Set>>, CommandError> {
+    for key in keys {
+        typed(ctx.db, key, Value::as_set)?;
+    }
+    let db = &*ctx.db;
+    Ok(keys
+        .iter()
+        .map(|key| match db.get(key) {
+            Some(StorageValue {
+                value: Value::Set(set),
+                ..
+            }) => Some(set),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Members of every set, walking the smallest one and stopping after `limit` of them
+fn intersection(sets: &[Option<&Set>], limit: usize) -> Vec<Bytes> {
+    // a missing key is an empty set, and so is the intersection
+    let Some(mut sets) = sets.iter().copied().collect::<Option<Vec<&Set>>>() else {
+        return vec![];
+    };
+    sets.sort_by_key(|set| set.len());
+    let (smallest, others) = sets.split_first().expect("at least one key");
+
+    smallest
+        .iter()
+        .map(|(member, _)| member)
+        .filter(|member| others.iter().all(|set| set.get(member).is_some()))
+        .take(limit)
+        .cloned()
+        .collect()
+}
+
+fn union(sets: &[Option<&Set>]) -> Vec<Bytes> {
+    let mut union = Set::default();
+    for (member, _) in sets.iter().flatten().flat_map(|set| set.iter()) {
+        union.insert(member.clone(), ());
+    }
+    union.iter().map(|(member, _)| member.clone()).collect()
+}
+
+// the members of the first set that are in none of the others
+fn difference(sets: &[Option<&Set>]) -> Vec<Bytes> {
+    let Some(first) = sets[0] else {
+        return vec![];
+    };
+    first
+        .iter()
+        .map(|(member, _)| member)
+        .filter(|member| {
+            sets[1..]
+                .iter()
+                .flatten()
+                .all(|set| set.get(member).is_none())
+        })
+        .cloned()
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+enum Algebra {
+    Inter,
+    Union,
+    Diff,
+}
+
+fn algebra(ctx: &mut Context, keys: &[Bytes], op: Algebra) -> Result<Vec<Bytes>, CommandError> {
+    let sets = operands(ctx, keys)?;
+    Ok(match op {
+        Algebra::Inter => intersection(&sets, usize::MAX),
+        Algebra::Union => union(&sets),
+        Algebra::Diff => difference(&sets),
+    })
+}
+
+fn algebra_generic(ctx: &mut Context, argv: &[Bytes], op: Algebra) -> CommandResult {
+    Ok(members_reply(algebra(ctx, &argv[1..], op)?))
+}
+
+// the destination is replaced whatever it held, or deleted when the result is empty
+fn algebra_store(ctx: &mut Context, argv: &[Bytes], op: Algebra) -> CommandResult {
+    let members = algebra(ctx, &argv[2..], op)?;
+    let len = members.len();
+
+    match len {
+        0 => {
+            ctx.db.remove(&argv[1]);
+        }
+        _ => {
+            let mut set = Set::default();
+            for member in members {
+                set.insert(member, ());
+            }
+            ctx.db
+                .insert(argv[1].clone(), StorageValue::new(Value::Set(set), None));
+        }
+    }
+    Ok(RespConcreteType::Int(len as i64))
+}
+
+fn sinter(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    algebra_generic(ctx, argv, Algebra::Inter)
+}
+
+fn sinterstore(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    algebra_store(ctx, argv, Algebra::Inter)
+}
+
+fn sunion(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    algebra_generic(ctx, argv, Algebra::Union)
+}
+
+fn sunionstore(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    algebra_store(ctx, argv, Algebra::Union)
+}
+
+fn sdiff(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    algebra_generic(ctx, argv, Algebra::Diff)
+}
+
+fn sdiffstore(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    algebra_store(ctx, argv, Algebra::Diff)
+}
+
+// a LIMIT of 0 means no limit
+fn sintercard(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let numkeys = match parse_int(&argv[1]) {
+        Ok(numkeys) if numkeys > 0 => numkeys as usize,
+        _ => return Err(CommandError::NumKeys),
+    };
+    let keys = argv
+        .get(2..2 + numkeys)
+        .ok_or(CommandError::NumKeysTooMany)?;
+
+    let limit = match &argv[2 + numkeys..] {
+        [] => 0,
+        [option, limit] if option.eq_ignore_ascii_case(b"limit") => match parse_int(limit) {
+            Ok(limit) if limit >= 0 => limit as usize,
+            _ => return Err(CommandError::LimitNegative),
+        },
+        _ => return Err(CommandError::Syntax),
+    };
+    let limit = match limit {
+        0 => usize::MAX,
+        limit => limit,
+    };
+
+    let sets = operands(ctx, keys)?;
+    Ok(RespConcreteType::Int(
+        intersection(&sets, limit).len() as i64
+    ))
+}
+
+fn sscan(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let cursor = parse_cursor(&argv[2])?;
+    let options = scan_options(&argv[3..], false)?;
+
+    let Some(set) = typed(ctx.db, &argv[1], Value::as_set)? else {
+        return Ok(scan_reply(0, []));
+    };
+    let mut members = Vec::new();
+    let cursor = scan_walk(cursor, options.count, &mut members, |cursor, members| {
+        set.scan(cursor, |member, _| members.push(member.clone()))
+    });
+    members.retain(|member| options.matches(member));
+
+    Ok(scan_reply(
+        cursor,
+        members.into_iter().map(RespConcreteType::BulkString),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command::tests::Server;
+
+    #[tokio::test]
+    async fn srandmember_takes_the_whole_count_range() {
+        let mut server = Server::new();
+        assert_eq!(server.run(&["SADD", "s", "a"]).await, ":1\r\n");
+        // unlike HRANDFIELD, there are no values to double the reply
+        assert_eq!(
+            server
+                .run(&["SRANDMEMBER", "s", "9223372036854775807"])
+                .await,
+            "*1\r\n$1\r\na\r\n"
+        );
+        assert_eq!(
+            server.run(&["SRANDMEMBER", "s", "-3"]).await,
+            "*3\r\n$1\r\na\r\n$1\r\na\r\n$1\r\na\r\n"
+        );
+        assert_eq!(
+            server.run(&["SRANDMEMBER", "s", "-9223372036854775808"]).await,
+            "-ERR value is out of range, value must between -9223372036854775807 and 9223372036854775807\r\n"
+        );
+    }
+}
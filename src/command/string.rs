@@ -0,0 +1,664 @@
+use bytes::{Bytes, BytesMut};
+
+use crate::config::Config;
+use crate::resp::RespConcreteType;
+use crate::storage::{StorageValue, Value};
+
+use super::{
+    parse_float, parse_int, typed, typed_or_create, CommandError, CommandFlag::*, CommandResult,
+    CommandSpec, Context,
+};
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("get", 2, get)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("string", "1.0.0", "Returns the string value of a key."),
+    CommandSpec::new("set", -3, set)
+        .flags(&[Write, Denyoom])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "1.0.0",
+            "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist.",
+        ),
+    CommandSpec::new("incr", 2, incr)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "1.0.0",
+            "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+        ),
+    CommandSpec::new("decr", 2, decr)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "1.0.0",
+            "Decrements the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+        ),
+    CommandSpec::new("incrby", 3, incrby)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "1.0.0",
+            "Increments the integer value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+        ),
+    CommandSpec::new("decrby", 3, decrby)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "1.0.0",
+            "Decrements a number from the integer value of a key. Uses 0 as initial value if the key doesn't exist.",
+        ),
+    CommandSpec::new("incrbyfloat", 3, incrbyfloat)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "2.6.0",
+            "Increment the floating point value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+        ),
+    CommandSpec::new("getdel", 2, getdel)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "6.2.0",
+            "Returns the string value of a key after deleting the key.",
+        ),
+    CommandSpec::new("getex", -2, getex)
+        .flags(&[Write, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "6.2.0",
+            "Returns the string value of a key after setting its expiration time.",
+        ),
+    CommandSpec::new("setnx", 3, setnx)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "1.0.0",
+            "Set the string value of a key only when the key doesn't exist.",
+        ),
+    CommandSpec::new("mget", -2, mget)
+        .flags(&[Readonly, Fast])
+        .keys(1, -1, 1)
+        .docs(
+            "string",
+            "1.0.0",
+            "Atomically returns the string values of one or more keys.",
+        ),
+    CommandSpec::new("mset", -3, mset)
+        .flags(&[Write, Denyoom])
+        .keys(1, -1, 2)
+        .docs(
+            "string",
+            "1.0.1",
+            "Atomically creates or modifies the string values of one or more keys.",
+        ),
+    CommandSpec::new("msetnx", -3, msetnx)
+        .flags(&[Write, Denyoom])
+        .keys(1, -1, 2)
+        .docs(
+            "string",
+            "1.0.1",
+            "Atomically modifies the string values of one or more keys only when all keys don't exist.",
+        ),
+    CommandSpec::new("append", 3, append)
+        .flags(&[Write, Denyoom, Fast])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "2.0.0",
+            "Appends a string to the value of a key. Creates the key if it doesn't exist.",
+        ),
+    CommandSpec::new("strlen", 2, strlen)
+        .flags(&[Readonly, Fast])
+        .keys(1, 1, 1)
+        .docs("string", "2.2.0", "Returns the length of a string value."),
+    CommandSpec::new("getrange", 4, getrange)
+        .flags(&[Readonly])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "2.4.0",
+            "Returns a substring of the string stored at a key.",
+        ),
+    CommandSpec::new("setrange", 4, setrange)
+        .flags(&[Write, Denyoom])
+        .keys(1, 1, 1)
+        .docs(
+            "string",
+            "2.2.0",
+            "Overwrites a part of a string value with another by an offset. Creates the key if it doesn't exist.",
+        ),
+    CommandSpec::new("lcs", -3, lcs)
+        .flags(&[Readonly])
+        .keys(1, 2, 1)
+        .docs("string", "7.0.0", "Finds the longest common substring."),
+];
+
+fn empty() -> Value {
+    Value::String(BytesMut::new())
+}
+
+fn get(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    match typed(ctx.db, &argv[1], Value::as_string)? {
+        Some(string) => Ok(RespConcreteType::BulkString(Bytes::copy_from_slice(string))),
+        None => Ok(RespConcreteType::NullBulkString),
+    }
+}
+
+#[derive(PartialEq, Eq)]
+enum SetCondition {
+    Nx,
+    Xx,
+}
+
+enum SetExpiry {
+    At(i64),
+    KeepTtl,
+}
+
+fn set(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let key = &argv[1];
+    let value = BytesMut::from(&argv[2][..]);
+
+    let mut condition = None;
+    let mut expiry = None;
+    let mut get = false;
+
+    let mut options = argv[3..].iter();
+    while let Some(option) = options.next() {
+        match option.to_ascii_lowercase().as_slice() {
+            b"nx" if condition != Some(SetCondition::Xx) => condition = Some(SetCondition::Nx),
+            b"xx" if condition != Some(SetCondition::Nx) => condition = Some(SetCondition::Xx),
+            b"get" => get = true,
+            b"keepttl" if expiry.is_none() => expiry = Some(SetExpiry::KeepTtl),
+            unit @ (b"ex" | b"px" | b"exat" | b"pxat") if expiry.is_none() => {
+                let time = options.next().ok_or(CommandError::Syntax)?;
+                expiry = Some(SetExpiry::At(expire_at(
+                    unit,
+                    parse_int(time)?,
+                    ctx.db.now(),
+                    "set",
+                )?));
+            }
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    let mut old = ctx.db.live(key);
+    // SET overwrites keys of any type, only GET needs the old value to be a string
+    let old_value = match old.as_mut().map(|old| old.value.as_string()) {
+        Some(Some(old)) => RespConcreteType::BulkString(Bytes::copy_from_slice(old)),
+        Some(None) if get => return Err(CommandError::WrongType),
+        _ => RespConcreteType::NullBulkString,
+    };
+
+    let applies = match condition {
+        Some(SetCondition::Nx) => old.is_none(),
+        Some(SetCondition::Xx) => old.is_some(),
+        None => true,
+    };
+    if !applies {
+        return Ok(match get {
+            true => old_value,
+            false => RespConcreteType::NullBulkString,
+        });
+    }
+
+    let expiry_at = match expiry {
+        None => None,
+        Some(SetExpiry::KeepTtl) => old.and_then(|old| old.expiry_at()),
+        Some(SetExpiry::At(expiry_at)) => Some(expiry_at),
+    };
+
+    ctx.db.insert(
+        key.clone(),
+        StorageValue::new(Value::String(value), expiry_at),
+    );
+    Ok(match get {
+        true => old_value,
+        false => RespConcreteType::ok(),
+    })
+}
+
+fn incr(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    incr_by(ctx, &argv[1], 1)
+}
+
+fn decr(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    incr_by(ctx, &argv[1], -1)
+}
+
+fn incrby(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    incr_by(ctx, &argv[1], parse_int(&argv[2])?)
+}
+
+fn decrby(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let decrement = parse_int(&argv[2])?;
+    let increment = decrement
+        .checked_neg()
+        .ok_or(CommandError::DecrementOverflow)?;
+    incr_by(ctx, &argv[1], increment)
+}
+
+fn incr_by(ctx: &mut Context, key: &Bytes, increment: i64) -> CommandResult {
+    let current = match typed(ctx.db, key, Value::as_string)? {
+        Some(string) => parse_int(string)?,
+        None => 0,
+    };
+    let value = current
+        .checked_add(increment)
+        .ok_or(CommandError::Overflow)?;
+
+    store(ctx, key, value.to_string().as_bytes());
+    Ok(RespConcreteType::Int(value))
+}
+
+fn incrbyfloat(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let key = &argv[1];
+    let increment = parse_float(&argv[2])?;
+
+    let current = match typed(ctx.db, key, Value::as_string)? {
+        Some(string) => parse_float(string)?,
+        None => 0.0,
+    };
+    let value = current + increment;
+    if !value.is_finite() {
+        return Err(CommandError::NanOrInfinity);
+    }
+
+    let value = format_float(value);
+    store(ctx, key, &value);
+    Ok(RespConcreteType::BulkString(value))
+}
+
+/// Formats the result of a float increment like redis does, which prints `%.17Lf` with the
+/// trailing zeros trimmed: never in exponent form and nothing past the 17th decimal. Within
+/// that, the digits are the shortest that read back as the same value, so integers stay exact
+pub fn format_float(value: f64) -> Bytes {
+    let formatted = value.to_string();
+    match formatted.split_once('.') {
+        Some((_, fraction)) if fraction.len() > 17 => {
+            let formatted = format!("{value:.17}");
+            Bytes::from(
+                formatted
+                    .trim_end_matches('0')
+                    .trim_end_matches('.')
+                    .to_string(),
+            )
+        }
+        _ => Bytes::from(formatted),
+    }
+}
+
+// replaces the value of a key in place, keeping its ttl, or creates the key
+fn store(ctx: &mut Context, key: &Bytes, value: &[u8]) {
+    let value = BytesMut::from(value);
+    match ctx.db.live(key) {
+        Some(entry) => entry.value = Value::String(value),
+        None => {
+            ctx.db
+                .insert(key.clone(), StorageValue::new(Value::String(value), None));
+        }
+    }
+}
+
+fn getdel(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    // taken out of the key rather than copied, it is deleted anyway
+    let Some(string) = typed(ctx.db, &argv[1], Value::as_string)?.map(|string| string.split())
+    else {
+        return Ok(RespConcreteType::NullBulkString);
+    };
+    ctx.db.remove(&argv[1]);
+    Ok(RespConcreteType::BulkString(string.freeze()))
+}
+
+enum GetExExpiry {
+    At(i64),
+    Persist,
+}
+
+fn getex(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let key = &argv[1];
+
+    let mut expiry = None;
+    let mut options = argv[2..].iter();
+    while let Some(option) = options.next() {
+        match option.to_ascii_lowercase().as_slice() {
+            b"persist" if expiry.is_none() => expiry = Some(GetExExpiry::Persist),
+            unit @ (b"ex" | b"px" | b"exat" | b"pxat") if expiry.is_none() => {
+                let time = options.next().ok_or(CommandError::Syntax)?;
+                expiry = Some(GetExExpiry::At(expire_at(
+                    unit,
+                    parse_int(time)?,
+                    ctx.db.now(),
+                    "getex",
+                )?));
+            }
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    let Some(string) =
+        typed(ctx.db, key, Value::as_string)?.map(|string| Bytes::copy_from_slice(string))
+    else {
+        return Ok(RespConcreteType::NullBulkString);
+    };
+    match expiry {
+        None => {}
+        // a deadline in the past deletes the key, the value is still returned
+        Some(GetExExpiry::At(expiry_at)) if expiry_at <= ctx.db.now() => {
+            ctx.db.remove(key);
+        }
+        Some(GetExExpiry::At(expiry_at)) => {
+            ctx.db.expire(key, Some(expiry_at));
+        }
+        Some(GetExExpiry::Persist) => {
+            ctx.db.expire(key, None);
+        }
+    }
+    Ok(RespConcreteType::BulkString(string))
+}
+
+fn setnx(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    if ctx.db.live(&argv[1]).is_some() {
+        return Ok(RespConcreteType::Int(0));
+    }
+    set_string(ctx, &argv[1], &argv[2]);
+    Ok(RespConcreteType::Int(1))
+}
+
+// keys that are missing or hold something other than a string are both nil
+fn mget(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    Ok(RespConcreteType::array(argv[1..].iter().map(|key| {
+        match ctx.db.live(key).and_then(|entry| entry.value.as_string()) {
+            Some(string) => RespConcreteType::BulkString(Bytes::copy_from_slice(string)),
+            None => RespConcreteType::NullBulkString,
+        }
+    })))
+}
+
+fn mset(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    for pair in pairs(argv)? {
+        set_string(ctx, &pair[0], &pair[1]);
+    }
+    Ok(RespConcreteType::ok())
+}
+
+// either every key is set or, when any of them exists, none is
+fn msetnx(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let pairs = pairs(argv)?;
+    if pairs.clone().any(|pair| ctx.db.live(&pair[0]).is_some()) {
+        return Ok(RespConcreteType::Int(0));
+    }
+    for pair in pairs {
+        set_string(ctx, &pair[0], &pair[1]);
+    }
+    Ok(RespConcreteType::Int(1))
+}
+
+// the key value pairs of MSET style commands
+fn pairs(argv: &[Bytes]) -> Result<std::slice::Chunks<'_, Bytes>, CommandError> {
+    match argv.len() % 2 == 0 {
+        true => Err(CommandError::WrongArity(
+            String::from_utf8_lossy(&argv[0]).to_lowercase(),
+        )),
+        false => Ok(argv[1..].chunks(2)),
+    }
+}
+
+// like SET, drops whatever the key held along with its ttl
+fn set_string(ctx: &mut Context, key: &Bytes, value: &Bytes) {
+    ctx.db.insert(
+        key.clone(),
+        StorageValue::new(Value::String(BytesMut::from(&value[..])), None),
+    );
+}
+
+fn append(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let key = &argv[1];
+    let len = match typed(ctx.db, key, Value::as_string)? {
+        None => {
+            store(ctx, key, &argv[2]);
+            argv[2].len()
+        }
+        // appended to in place, so repeated appends cost only what they add
+        Some(string) => {
+            check_string_len(ctx.config, string.len() as u64 + argv[2].len() as u64)?;
+            string.extend_from_slice(&argv[2]);
+            string.len()
+        }
+    };
+    Ok(RespConcreteType::Int(len as i64))
+}
+
+fn strlen(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let len = typed(ctx.db, &argv[1], Value::as_string)?.map_or(0, |string| string.len());
+    Ok(RespConcreteType::Int(len as i64))
+}
+
+// negative offsets count from the end, the range is inclusive and clamped to the string
+fn getrange(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (mut start, mut end) = (parse_int(&argv[2])?, parse_int(&argv[3])?);
+    let string = typed(ctx.db, &argv[1], Value::as_string)?.map_or(&[][..], |string| &string[..]);
+    let len = string.len() as i64;
+
+    if start < 0 && end < 0 && start > end {
+        return Ok(RespConcreteType::bulk(Bytes::new()));
+    }
+    if start < 0 {
+        start = (len + start).max(0);
+    }
+    if end < 0 {
+        end = (len + end).max(0);
+    }
+    end = end.min(len - 1);
+    if start > end || len == 0 {
+        return Ok(RespConcreteType::bulk(Bytes::new()));
+    }
+    Ok(RespConcreteType::BulkString(Bytes::copy_from_slice(
+        &string[start as usize..=end as usize],
+    )))
+}
+
+// the string is zero padded up to `offset` when it is shorter
+fn setrange(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let key = &argv[1];
+    let offset = parse_int(&argv[2])?;
+    if offset < 0 {
+        return Err(CommandError::OffsetOutOfRange);
+    }
+    let offset = offset as usize;
+    let patch = &argv[3];
+
+    let current = typed(ctx.db, key, Value::as_string)?;
+    // an empty patch changes nothing, not even creating the key
+    if patch.is_empty() {
+        let len = current.map_or(0, |string| string.len());
+        return Ok(RespConcreteType::Int(len as i64));
+    }
+    check_string_len(ctx.config, offset as u64 + patch.len() as u64)?;
+
+    // patched in place, only growing the string when it is too short
+    let string = typed_or_create(ctx.db, key, Value::as_string, empty)?;
+    if string.len() < offset + patch.len() {
+        string.resize(offset + patch.len(), 0);
+    }
+    string[offset..offset + patch.len()].copy_from_slice(patch);
+    Ok(RespConcreteType::Int(string.len() as i64))
+}
+
+fn check_string_len(config: &Config, len: u64) -> Result<(), CommandError> {
+    match len > config.proto.max_bulk_len as u64 {
+        true => Err(CommandError::StringTooLong),
+        false => Ok(()),
+    }
+}
+
+fn lcs(ctx: &mut Context, argv: &[Bytes]) -> CommandResult {
+    let (mut len_only, mut idx, mut with_match_len) = (false, false, false);
+    let mut min_match_len = 0;
+
+    let mut options = argv[3..].iter();
+    while let Some(option) = options.next() {
+        match option.to_ascii_lowercase().as_slice() {
+            b"len" => len_only = true,
+            b"idx" => idx = true,
+            b"withmatchlen" => with_match_len = true,
+            b"minmatchlen" => {
+                let len = options.next().ok_or(CommandError::Syntax)?;
+                min_match_len = parse_int(len)?.max(0) as usize;
+            }
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+    if len_only && idx {
+        return Err(CommandError::LcsLenAndIdx);
+    }
+
+    let a = lcs_operand(ctx, &argv[1])?;
+    let b = lcs_operand(ctx, &argv[2])?;
+
+    // the dynamic programming table, `table[i][j]` is the lcs length of a[..i] and b[..j]
+    let width = b.len() + 1;
+    let cells = (a.len() + 1)
+        .checked_mul(width)
+        .filter(|cells| cells.saturating_mul(4) <= ctx.config.proto.max_bulk_len)
+        .ok_or(CommandError::LcsTooLarge)?;
+    let mut table = vec![0u32; cells];
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            table[i * width + j] = match a[i - 1] == b[j - 1] {
+                true => table[(i - 1) * width + j - 1] + 1,
+                false => table[(i - 1) * width + j].max(table[i * width + j - 1]),
+            };
+        }
+    }
+    let len = table[a.len() * width + b.len()] as usize;
+
+    if len_only {
+        return Ok(RespConcreteType::Int(len as i64));
+    }
+
+    // walk back from the end, collecting the common string and, for IDX, the ranges
+    // where it matches contiguously in both strings
+    let mut common = vec![0; len];
+    let mut matches = Vec::new();
+    let (mut i, mut j, mut k) = (a.len(), b.len(), len);
+    let mut range: Option<(usize, usize, usize, usize)> = None;
+    while i > 0 && j > 0 {
+        let mut emit = false;
+        if a[i - 1] == b[j - 1] {
+            common[k - 1] = a[i - 1];
+            range = match range {
+                None => Some((i - 1, i - 1, j - 1, j - 1)),
+                Some((a_start, a_end, b_start, b_end)) if a_start == i && b_start == j => {
+                    Some((a_start - 1, a_end, b_start - 1, b_end))
+                }
+                Some(range) => {
+                    emit = true;
+                    Some(range)
+                }
+            };
+            if range.is_some_and(|(a_start, _, b_start, _)| a_start == 0 || b_start == 0) {
+                emit = true;
+            }
+            k -= 1;
+            i -= 1;
+            j -= 1;
+        } else {
+            match table[(i - 1) * width + j] > table[i * width + j - 1] {
+                true => i -= 1,
+                false => j -= 1,
+            }
+            emit = range.is_some();
+        }
+
+        if let Some((a_start, a_end, b_start, b_end)) = if emit { range.take() } else { None } {
+            let match_len = a_end - a_start + 1;
+            if match_len >= min_match_len {
+                let mut entry = vec![
+                    RespConcreteType::array(
+                        [a_start, a_end].map(|i| RespConcreteType::Int(i as i64)),
+                    ),
+                    RespConcreteType::array(
+                        [b_start, b_end].map(|i| RespConcreteType::Int(i as i64)),
+                    ),
+                ];
+                if with_match_len {
+                    entry.push(RespConcreteType::Int(match_len as i64));
+                }
+                matches.push(RespConcreteType::array(entry));
+            }
+        }
+    }
+
+    if !idx {
+        return Ok(RespConcreteType::bulk(common));
+    }
+    Ok(RespConcreteType::Map(vec![
+        (
+            RespConcreteType::bulk("matches"),
+            RespConcreteType::array(matches),
+        ),
+        (
+            RespConcreteType::bulk("len"),
+            RespConcreteType::Int(len as i64),
+        ),
+    ]))
+}
+
+// a missing key is an empty string, any other type is an error
+fn lcs_operand(ctx: &mut Context, key: &Bytes) -> Result<Bytes, CommandError> {
+    match ctx.db.live(key).map(|entry| entry.value.as_string()) {
+        None => Ok(Bytes::new()),
+        Some(Some(string)) => Ok(Bytes::copy_from_slice(string)),
+        Some(None) => Err(CommandError::LcsNotString),
+    }
+}
+
+/// Resolves an `EX`/`PX`/`EXAT`/`PXAT` style argument into a deadline in unix milliseconds
+pub fn expire_at(unit: &[u8], time: i64, now: i64, command: &str) -> Result<i64, CommandError> {
+    let invalid = || CommandError::InvalidExpireTime(command.to_string());
+    if time <= 0 {
+        return Err(invalid());
+    }
+
+    let millis = match unit {
+        b"ex" | b"exat" => time.checked_mul(1000).ok_or_else(invalid)?,
+        _ => time,
+    };
+    match unit {
+        b"exat" | b"pxat" => Ok(millis),
+        _ => millis.checked_add(now).ok_or_else(invalid),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_float;
+
+    #[test]
+    fn floats_format_like_redis() {
+        assert_eq!(format_float(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(format_float(0.3), "0.3");
+        assert_eq!(format_float(10.5 + 0.1), "10.6");
+        assert_eq!(format_float(5200.0), "5200");
+        assert_eq!(format_float(1e20), "100000000000000000000");
+        assert_eq!(format_float(1.5e-10), "0.00000000015");
+        assert_eq!(format_float(1e-20), "0");
+        // integers f64 holds exactly keep every digit
+        assert_eq!(format_float(1234567890123456.0 + 1.0), "1234567890123457");
+        assert_eq!(format_float(-1234567890123456.0), "-1234567890123456");
+        assert_eq!(format_float(12345678901234568.0), "12345678901234568");
+    }
+}
@@ -0,0 +1,85 @@
+use thiserror::Error;
+
+use crate::resp::RespLimits;
+
+/// Server settings, given on the command line as `--name value` pairs like redis-server
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub proto: RespLimits,
+    pub client_query_buffer_limit: usize,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("unknown option '{0}'")]
+    UnknownOption(String),
+    #[error("missing value for '{0}'")]
+    MissingValue(String),
+    #[error("invalid value '{1}' for '{0}'")]
+    BadValue(String, String),
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            proto: RespLimits::default(),
+            client_query_buffer_limit: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| ConfigError::UnknownOption(arg.clone()))?
+                .to_ascii_lowercase();
+            let value = args
+                .next()
+                .ok_or_else(|| ConfigError::MissingValue(name.clone()))?;
+            let bad_value = || ConfigError::BadValue(name.clone(), value.clone());
+
+            match name.as_str() {
+                "proto-max-bulk-len" => {
+                    config.proto.max_bulk_len = memory(&value).ok_or_else(bad_value)?
+                }
+                "proto-max-multibulk-len" => {
+                    config.proto.max_multibulk_len = value.parse().map_err(|_| bad_value())?
+                }
+                "proto-max-nesting" => {
+                    config.proto.max_nesting_depth = value.parse().map_err(|_| bad_value())?
+                }
+                "proto-max-inline-len" => {
+                    config.proto.max_inline_len = memory(&value).ok_or_else(bad_value)?
+                }
+                "client-query-buffer-limit" => {
+                    config.client_query_buffer_limit = memory(&value).ok_or_else(bad_value)?
+                }
+                _ => return Err(ConfigError::UnknownOption(arg)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+// sizes accept the same units as redis.conf, k/m/g are powers of 1000 and kb/mb/gb of 1024
+fn memory(value: &str) -> Option<usize> {
+    let value = value.to_ascii_lowercase();
+    let digits = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let multiplier = match &value[digits.len()..] {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    digits.parse::<usize>().ok()?.checked_mul(multiplier)
+}
@@ -0,0 +1,237 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use crate::random;
+
+const MIN_BUCKETS: usize = 4;
+
+/// A chained hash table with a power of two number of buckets, like redis' dict.
+/// Unlike `HashMap` it can be walked with a cursor that stays valid across inserts,
+/// deletes and resizes, and it can hand out random entries cheaply
+#[derive(Debug, Clone)]
+pub struct Dict<K, V> {
+    buckets: Vec<Vec<(K, V)>>,
+    len: usize,
+    hasher: RandomState,
+}
+
+impl<K: Hash + Eq, V> Default for Dict<K, V> {
+    fn default() -> Self {
+        Dict {
+            buckets: Vec::new(),
+            len: 0,
+            hasher: RandomState::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq, V> Dict<K, V> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn bucket(&self, key: &K) -> usize {
+        let mut hasher = self.hasher.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish() as usize & (self.buckets.len() - 1)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        self.buckets[self.bucket(key)]
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let bucket = self.bucket(key);
+        self.buckets[bucket]
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(existing) = self.get_mut(&key) {
+            return Some(std::mem::replace(existing, value));
+        }
+
+        // grow at a load factor of one
+        if self.len >= self.buckets.len() {
+            self.resize((self.len + 1).next_power_of_two().max(MIN_BUCKETS));
+        }
+        let bucket = self.bucket(&key);
+        self.buckets[bucket].push((key, value));
+        self.len += 1;
+        None
+    }
+
+    pub fn get_or_insert_with(&mut self, key: K, value: impl FnOnce() -> V) -> &mut V {
+        if self.len >= self.buckets.len() && self.get(&key).is_none() {
+            self.resize((self.len + 1).next_power_of_two().max(MIN_BUCKETS));
+        }
+        let bucket = self.bucket(&key);
+        let bucket = &mut self.buckets[bucket];
+        let i = match bucket.iter().position(|(k, _)| *k == key) {
+            Some(i) => i,
+            None => {
+                bucket.push((key, value()));
+                self.len += 1;
+                bucket.len() - 1
+            }
+        };
+        &mut bucket[i].1
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let bucket = self.bucket(key);
+        let i = self.buckets[bucket].iter().position(|(k, _)| k == key)?;
+        let (_, value) = self.buckets[bucket].swap_remove(i);
+        self.len -= 1;
+
+        // shrink once mostly empty, so scanning and sampling don't wade through empty buckets
+        if self.buckets.len() > MIN_BUCKETS && self.len * 8 < self.buckets.len() {
+            self.resize(self.len.next_power_of_two().max(MIN_BUCKETS));
+        }
+        Some(value)
+    }
+
+    fn resize(&mut self, size: usize) {
+        let old = std::mem::replace(&mut self.buckets, (0..size).map(|_| Vec::new()).collect());
+        for (key, value) in old.into_iter().flatten() {
+            let bucket = self.bucket(&key);
+            self.buckets[bucket].push((key, value));
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.buckets.iter().flatten().map(|(k, v)| (k, v))
+    }
+
+    /// Visits the entries of one bucket and returns the cursor to continue from, 0 once done.
+    ///
+    /// The cursor counts with its bits reversed, so buckets that a resize splits or merges
+    /// are always ahead of it or behind it together. Every entry present for the whole scan
+    /// is visited at least once, some may be visited more than once
+    pub fn scan(&self, cursor: u64, mut visit: impl FnMut(&K, &V)) -> u64 {
+        if self.buckets.is_empty() {
+            return 0;
+        }
+        let mask = self.buckets.len() as u64 - 1;
+        for (k, v) in &self.buckets[(cursor & mask) as usize] {
+            visit(k, v);
+        }
+
+        // increment the bits above the mask, from the top
+        let cursor = cursor | !mask;
+        cursor.reverse_bits().wrapping_add(1).reverse_bits()
+    }
+
+    /// A random entry, picked by bucket first so not perfectly uniform
+    pub fn random(&self) -> Option<(&K, &V)> {
+        if self.len == 0 {
+            return None;
+        }
+        // the load factor is kept above 1/8, so this doesn't spin for long
+        loop {
+            let bucket = &self.buckets[random::below(self.buckets.len())];
+            if !bucket.is_empty() {
+                let (k, v) = &bucket[random::below(bucket.len())];
+                return Some((k, v));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::Dict;
+
+    fn dict(keys: std::ops::Range<u32>) -> Dict<u32, ()> {
+        let mut dict = Dict::default();
+        for key in keys {
+            dict.insert(key, ());
+        }
+        dict
+    }
+
+    // scans the whole dict, calling `between` after every step
+    fn scan_all(
+        dict: &mut Dict<u32, ()>,
+        mut between: impl FnMut(&mut Dict<u32, ()>),
+    ) -> HashSet<u32> {
+        let (mut seen, mut cursor) = (HashSet::new(), 0);
+        loop {
+            cursor = dict.scan(cursor, |&key, _| {
+                seen.insert(key);
+            });
+            if cursor == 0 {
+                return seen;
+            }
+            between(dict);
+        }
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let mut dict = dict(0..100);
+        assert_eq!(dict.len(), 100);
+        assert_eq!(dict.insert(7, ()), Some(()));
+        assert!(dict.get(&99).is_some());
+        assert!(dict.get(&100).is_none());
+        for key in 0..100 {
+            assert_eq!(dict.remove(&key), Some(()));
+        }
+        assert!(dict.is_empty());
+        assert_eq!(dict.remove(&0), None);
+    }
+
+    #[test]
+    fn scan_visits_everything() {
+        let mut dict = dict(0..1000);
+        assert_eq!(scan_all(&mut dict, |_| {}), (0..1000).collect());
+        assert_eq!(Dict::<u32, ()>::default().scan(0, |_, _| panic!()), 0);
+    }
+
+    #[test]
+    fn scan_survives_growing() {
+        let mut dict = dict(0..100);
+        let mut next = 100;
+        let seen = scan_all(&mut dict, |dict| {
+            for key in next..(next + 50).min(5000) {
+                dict.insert(key, ());
+            }
+            next += 50;
+        });
+        assert!((0..100).all(|key| seen.contains(&key)));
+    }
+
+    #[test]
+    fn scan_survives_shrinking() {
+        // only the keys below 20 stay for the whole scan
+        let mut dict = dict(0..2000);
+        let mut next = 20;
+        let seen = scan_all(&mut dict, |dict| {
+            for key in next..(next + 200).min(2000) {
+                dict.remove(&key);
+            }
+            next += 200;
+        });
+        assert!((0..20).all(|key| seen.contains(&key)));
+    }
+}
@@ -0,0 +1,144 @@
+/// Glob-style matching as in redis' `stringmatchlen`: `*`, `?`, `[abc]`, `[^abc]`, `[a-z]`
+/// and `\` to escape the next character
+pub fn matches(pattern: &[u8], string: &[u8]) -> bool {
+    matches_from(pattern, string, &mut false, 0)
+}
+
+// like redis, patterns nesting stars deeper than this never match
+const MAX_NESTING: usize = 1000;
+
+// `skip_longer` is redis' `skipLongerMatches`: once what follows a star matches nowhere in
+// the rest of the string, the stars before it can't help by eating more, which keeps patterns
+// like `*a*a*a*b` from backtracking exponentially
+fn matches_from(pattern: &[u8], string: &[u8], skip_longer: &mut bool, nesting: usize) -> bool {
+    if nesting > MAX_NESTING {
+        return false;
+    }
+    let (mut p, mut s) = (0, 0);
+
+    while p < pattern.len() && s < string.len() {
+        match pattern[p] {
+            b'*' => {
+                while pattern.get(p + 1) == Some(&b'*') {
+                    p += 1;
+                }
+                if p + 1 == pattern.len() {
+                    return true;
+                }
+                for start in s..string.len() {
+                    if matches_from(
+                        &pattern[p + 1..],
+                        &string[start..],
+                        skip_longer,
+                        nesting + 1,
+                    ) {
+                        return true;
+                    }
+                    if *skip_longer {
+                        return false;
+                    }
+                }
+                *skip_longer = true;
+                return false;
+            }
+            b'?' => s += 1,
+            b'[' => {
+                p += 1;
+                let not = pattern.get(p) == Some(&b'^');
+                if not {
+                    p += 1;
+                }
+
+                let mut matched = false;
+                loop {
+                    match pattern.get(p) {
+                        // an unterminated class ends with the pattern
+                        None => {
+                            p -= 1;
+                            break;
+                        }
+                        Some(b'\\') if p + 1 < pattern.len() => {
+                            p += 1;
+                            matched |= pattern[p] == string[s];
+                        }
+                        Some(b']') => break,
+                        Some(&start) if p + 2 < pattern.len() && pattern[p + 1] == b'-' => {
+                            let end = pattern[p + 2];
+                            let (low, high) = (start.min(end), start.max(end));
+                            matched |= (low..=high).contains(&string[s]);
+                            p += 2;
+                        }
+                        Some(&c) => matched |= c == string[s],
+                    }
+                    p += 1;
+                }
+
+                if matched == not {
+                    return false;
+                }
+                s += 1;
+            }
+            b'\\' if p + 1 < pattern.len() => {
+                p += 1;
+                if pattern[p] != string[s] {
+                    return false;
+                }
+                s += 1;
+            }
+            c => {
+                if c != string[s] {
+                    return false;
+                }
+                s += 1;
+            }
+        }
+        p += 1;
+    }
+
+    // the string is used up, only stars may be left of the pattern
+    s == string.len() && pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn stars_and_question_marks() {
+        assert!(matches(b"*", b""));
+        assert!(matches(b"*", b"anything"));
+        assert!(matches(b"h?llo", b"hello"));
+        assert!(!matches(b"h?llo", b"hllo"));
+        assert!(matches(b"h*llo", b"hllo"));
+        assert!(matches(b"h*llo", b"heeeello"));
+        assert!(matches(b"**a**", b"bab"));
+        assert!(!matches(b"a*", b"ba"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(matches(b"h[ae]llo", b"hallo"));
+        assert!(!matches(b"h[ae]llo", b"hillo"));
+        assert!(matches(b"h[^e]llo", b"hallo"));
+        assert!(!matches(b"h[^e]llo", b"hello"));
+        assert!(matches(b"h[a-b]llo", b"hbllo"));
+        assert!(matches(b"h[b-a]llo", b"hallo"));
+        assert!(matches(b"[\\]]", b"]"));
+        // an unterminated class runs to the end of the pattern
+        assert!(matches(b"[ab", b"b"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches(b"h\\*llo", b"h*llo"));
+        assert!(!matches(b"h\\*llo", b"hello"));
+        assert!(matches(b"a\\", b"a\\"));
+    }
+
+    #[test]
+    fn many_stars_do_not_backtrack_exponentially() {
+        let string = [b'a'; 40];
+        assert!(!matches(b"*a*a*a*a*a*a*a*a*a*b", &string));
+        assert!(matches(b"*a*a*a*a*a*a*a*a*a*a", &string));
+    }
+}
@@ -1,6 +1,9 @@
 use bytes::{Bytes, BytesMut};
 
-use resp::RespConcreteType;
+use command::Client;
+use config::Config;
+use resp::{encode::encode, RespConcreteType, RespError};
+use storage::{Db, Storage};
 
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
@@ -8,44 +11,34 @@ use tokio::{
     sync::RwLock,
 };
 
-use std::time::{Duration, SystemTime};
-use std::{collections::HashMap, sync::Arc};
-
-enum Command {
-    Ping,
-    Set(SetCommand),
-    Get(String),
-    Echo(String),
-    Error(Bytes),
-}
-
-struct SetCommand {
-    key: String,
-    value: String,
-    expiry_at: Option<SystemTime>,
-}
-
-#[derive(Debug)]
-struct StorageValue {
-    value: String,
-    expiry_at: Option<SystemTime>,
-}
-
-enum CommandError {
-    UnknownCommand(String),
-    BadLength(usize),
-    BadExpiry(String),
-}
-
-type Storage = Arc<RwLock<HashMap<String, StorageValue>>>;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
 const INTERFACE: &str = "127.0.0.1";
 const PORT: &str = "6379";
 
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+mod blocking;
+mod clock;
+mod command;
+mod config;
+mod dict;
+mod glob;
+mod random;
 mod resp;
+mod storage;
 
 #[tokio::main]
 async fn main() {
+    let config = Config::from_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("Invalid arguments: {e}");
+        std::process::exit(1);
+    });
+    let config = Arc::new(config);
+
     // You can use print statements as follows for debugging, they'll be visible when running tests.
     println!("Starting Server on {} at port {}", INTERFACE, PORT);
     let listener = TcpListener::bind(format!("{}:{}", INTERFACE, PORT))
@@ -54,222 +47,156 @@ async fn main() {
 
     println!("Listening at {}", listener.local_addr().unwrap());
 
-    let storage: Storage = Arc::new(RwLock::new(HashMap::new()));
+    let storage: Storage = Arc::new(RwLock::new(Db::default()));
+    tokio::spawn(storage::active_expire(storage.clone()));
 
     loop {
-        let (stream, addr) = listener.accept().await.unwrap();
+        let (stream, addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            // failing to accept one client (e.g. out of file descriptors) must not stop the server
+            Err(e) => {
+                println!("Could not accept connection: {e}");
+                continue;
+            }
+        };
         // A new task is spawned for each inbound socket. The socket is
         // moved to the new ta sk and processed there.
         println!("New Connection at {addr}");
         let st = storage.clone();
-        tokio::spawn(async {
-            process(stream, st).await;
+        let cfg = config.clone();
+        let client_id = NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed);
+        tokio::spawn(async move {
+            process(stream, st, cfg, client_id).await;
         });
     }
 }
 
-async fn process(mut stream: TcpStream, storage: Storage) {
+async fn process(mut stream: TcpStream, storage: Storage, config: Arc<Config>, client_id: u64) {
     let mut buf = BytesMut::with_capacity(20);
     let mut partial: Option<resp::RespTypePartialable> = None;
-
-    loop {
-        let st = storage.clone();
-        let s = stream
-            .read_buf(&mut buf)
-            .await
-            .expect("Could not read message");
-
-        if s == 0 {
-            continue;
-        }
-
-        let parse_result = resp::parse(&mut buf, partial).expect("Unexpected parsing failure");
-
-        match parse_result {
-            resp::Resp::Partial(partial_res) => {
-                partial = Some(partial_res);
-                continue;
-            }
-            resp::Resp::Concrete(res) => {
-                partial = None;
-                let command = parse_command(res)
-                    .unwrap_or_else(|_| Command::Error(Bytes::from("+Invalid Command\r\n")));
-                handle_command(command, &mut stream, st).await;
+    // bytes already consumed into the partial state of the request being read
+    let mut pending = 0;
+    let mut client = Client::new(client_id);
+
+    let mut out = BytesMut::new();
+
+    'connection: while !client.closing {
+        match stream.read_buf(&mut buf).await {
+            // the client closed its side, anything left in a partial request is dropped
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                println!("Could not read from client {client_id}: {e}");
+                break;
             }
         }
-    }
-}
 
-fn parse_command(res: RespConcreteType) -> Result<Command, CommandError> {
-    match res {
-        RespConcreteType::Array(mut array) => match array.pop_front() {
-            Some(RespConcreteType::BulkString(command)) => match command.to_lowercase().as_str() {
-                "ping" => Ok(Command::Ping),
-                "echo" => match &array[0] {
-                    RespConcreteType::BulkString(arg) => Ok(Command::Echo(arg.to_string())),
-                    _ => Err(CommandError::UnknownCommand(
-                        "Invalid Echo Command".to_string(),
-                    )),
-                },
-                "set" => {
-                    if array.len() < 2 {
-                        return Err(CommandError::BadLength(array.len()));
+        // a single read may carry many pipelined commands, drain every complete one
+        // and keep the partial state of a trailing fragment for the next read
+        while !buf.is_empty() && !client.closing {
+            let available = buf.len();
+            let parse_result = resp::parse_request(&mut buf, partial.take(), &config.proto)
+                .and_then(|parsed| {
+                    pending = match parsed {
+                        resp::Resp::Partial(_) => pending + available - buf.len(),
+                        resp::Resp::Concrete(_) => 0,
+                    };
+                    match pending + buf.len() > config.client_query_buffer_limit {
+                        true => Err(RespError::QueryBufferLimit),
+                        false => Ok(parsed),
                     }
+                });
 
-                    let key = array.pop_front();
-                    let value = array.pop_front();
-
-                    let expiry_at = match array.pop_front() {
-                        Some(RespConcreteType::BulkString(exp)) => {
-                            if exp.to_ascii_lowercase().as_str() == "px" {
-                                let time = array.pop_front();
-                                match time {
-                                    Some(RespConcreteType::BulkString(time)) => {
-                                        let time = time
-                                            .parse::<u64>()
-                                            .map_err(|_| CommandError::BadExpiry(time))?;
-
-                                        SystemTime::now().checked_add(Duration::from_millis(time))
+            match parse_result {
+                Err(e) => protocol_error(e, &mut client, &mut out),
+                Ok(resp::Resp::Partial(partial_res)) => {
+                    partial = Some(partial_res);
+                    break;
+                }
+                // an empty multibulk is not a command, it is skipped like redis does
+                Ok(resp::Resp::Concrete(RespConcreteType::Array(array))) if array.is_empty() => {}
+                Ok(resp::Resp::Concrete(RespConcreteType::Array(array))) => {
+                    // `parse_request` guarantees every argument is a bulk string
+                    let argv: Vec<Bytes> = array
+                        .into_iter()
+                        .filter_map(|arg| match arg {
+                            RespConcreteType::BulkString(arg) => Some(arg),
+                            _ => None,
+                        })
+                        .collect();
+
+                    let reply = {
+                        let execute = command::execute(argv, &storage, &config, &mut client);
+                        tokio::pin!(execute);
+                        let done = tokio::select! {
+                            biased;
+                            reply = &mut execute => Some(reply),
+                            _ = async {} => None,
+                        };
+                        match done {
+                            Some(reply) => Ok(reply),
+                            None => {
+                                // a command that suspends, usually blocked, must not hold back
+                                // the replies to the commands pipelined before it
+                                if let Err(e) = flush(&mut stream, &mut out).await {
+                                    println!("Could not write to client {client_id}: {e}");
+                                    break 'connection;
+                                }
+                                // keep reading while it waits, so a client that hangs up is
+                                // noticed and no longer served; what else it sends waits in
+                                // buf, within the same limit as any other query
+                                loop {
+                                    tokio::select! {
+                                        reply = &mut execute => break Ok(reply),
+                                        read = stream.read_buf(&mut buf) => match read {
+                                            Ok(0) | Err(_) => break Err(None),
+                                            Ok(_) if buf.len() > config.client_query_buffer_limit => {
+                                                break Err(Some(RespError::QueryBufferLimit))
+                                            }
+                                            Ok(_) => {}
+                                        },
                                     }
-                                    _ => return Err(CommandError::BadLength(array.len())),
                                 }
-                            } else {
-                                // todo handle error of unknown args
-                                return Err(CommandError::BadLength(array.len()));
                             }
                         }
-                        _ => None,
                     };
-
-                    match (key, value) {
-                        (
-                            Some(RespConcreteType::BulkString(key)),
-                            Some(RespConcreteType::BulkString(value)),
-                        ) => Ok(Command::Set(SetCommand {
-                            key,
-                            value,
-                            expiry_at,
-                        })),
-                        _ => Err(CommandError::UnknownCommand(
-                            "Invalid Set Command".to_string(),
-                        )),
-                    }
-                }
-                "get" => {
-                    if array.len() != 1 {
-                        return Err(CommandError::BadLength(array.len()));
-                    }
-
-                    let key = array.pop_front();
-
-                    match key {
-                        Some(RespConcreteType::BulkString(key)) => Ok(Command::Get(key)),
-                        _ => Err(CommandError::UnknownCommand(
-                            "Invalid Get Command".to_string(),
-                        )),
+                    match reply {
+                        Ok(reply) => encode(&reply, client.protocol, &mut out),
+                        Err(Some(e)) => protocol_error(e, &mut client, &mut out),
+                        Err(None) => {
+                            println!("Client {client_id} went away while blocked");
+                            break 'connection;
+                        }
                     }
                 }
-                _ => Err(CommandError::UnknownCommand(command.to_string())),
-            },
-            _ => panic!("Unknown command"),
-        },
-        _ => panic!("Unknown command"),
-    }
-}
-
-// async fn process_all(
-//     mut buf: &mut BytesMut,
-//     mut stream: TcpStream,
-//     partial: Option<resp::RespTypePartialable>,
-// ) -> Resp {
-//     let s = stream
-//         .read_buf(&mut buf)
-//         .await
-//         .expect("Could not read message");
-
-//     if s == 0 {
-//         return process_all(buf, stream, partial).await;
-//     }
-
-//     let parse_result = resp::parse(&mut buf, partial).expect("damn");
-
-//     match parse_result {
-//         resp::Resp::Partial(partial_res) => {
-//             println!("Partial: {:?}", partial_res);
-//             return process_all(buf, stream, Some(partial_res)).await;
-//         }
-//         concrete_type => concrete_type,
-//     }
-// }
-
-async fn handle_command(command: Command, stream: &mut TcpStream, storage: Storage) {
-    match command {
-        Command::Ping => {
-            stream
-                .write_all("+PONG\r\n".as_bytes())
-                .await
-                .expect("could not write to buffer");
-        }
-        Command::Echo(arg) => {
-            stream
-                .write_all(format!("${}\r\n{arg}\r\n", arg.len()).as_bytes())
-                .await
-                .expect("could not write to buffer");
-        }
-        Command::Error(arg) => {
-            stream
-                .write_all(&arg)
-                .await
-                .expect("could not write to buffer");
+                // a null multibulk carries no command either
+                Ok(resp::Resp::Concrete(_)) => {}
+            }
         }
-        Command::Set(SetCommand {
-            key,
-            value,
-            expiry_at,
-        }) => {
-            let mut storage = storage.write().await;
 
-            storage.insert(key, StorageValue { expiry_at, value });
-            stream
-                .write_all("+OK\r\n".as_bytes())
-                .await
-                .expect("could not write to buffer");
+        // replies to everything parsed from this read go out in a single write
+        if let Err(e) = flush(&mut stream, &mut out).await {
+            println!("Could not write to client {client_id}: {e}");
+            break;
         }
-        Command::Get(key) => {
-            let rstorage = storage.read().await;
-            let value = rstorage.get(&key);
+    }
 
-            match value {
-                Some(value) => {
-                    let expiry_at = value.expiry_at;
-                    if let Some(expiry_at) = expiry_at {
-                        let now = SystemTime::now();
-                        if now > expiry_at {
-                            drop(rstorage);
-                            let mut storage = storage.write().await;
-                            storage.remove(&key);
+    println!("Closing connection {client_id}");
+}
 
-                            return stream
-                                .write_all("$-1\r\n".as_bytes())
-                                .await
-                                .expect("could not write to buffer");
-                        }
-                    }
+// the stream cannot be resynchronised after a protocol error, so report it after
+// any replies already due and close the connection
+fn protocol_error(error: RespError, client: &mut Client, out: &mut BytesMut) {
+    println!("Protocol error from client {}: {error}", client.id);
+    let error = RespConcreteType::Error(format!("ERR Protocol error: {error}"));
+    encode(&error, client.protocol, out);
+    client.closing = true;
+}
 
-                    stream
-                        .write_all(
-                            format!("${}\r\n{}\r\n", value.value.len(), value.value).as_bytes(),
-                        )
-                        .await
-                        .expect("could not write to buffer");
-                }
-                None => {
-                    stream
-                        .write_all("$-1\r\n".as_bytes())
-                        .await
-                        .expect("could not write to buffer");
-                }
-            }
-        }
-    };
+async fn flush(stream: &mut TcpStream, out: &mut BytesMut) -> std::io::Result<()> {
+    if !out.is_empty() {
+        stream.write_all(out).await?;
+        out.clear();
+    }
+    Ok(())
 }
@@ -0,0 +1,44 @@
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+// a xorshift64* generator per thread, good enough for sampling keys but not for anything secret
+thread_local! {
+    static STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+}
+
+pub fn next_u64() -> u64 {
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    })
+}
+
+/// A uniformly distributed index in `0..n`, `n` must not be zero
+pub fn below(n: usize) -> usize {
+    (next_u64() % n as u64) as usize
+}
+
+/// Up to `count` distinct items in random order, by shuffling only the part that is kept
+pub fn pick<T>(mut items: Vec<T>, count: usize) -> Vec<T> {
+    let count = count.min(items.len());
+    for i in 0..count {
+        let j = i + below(items.len() - i);
+        items.swap(i, j);
+    }
+    items.truncate(count);
+    items
+}
+
+/// `count` items drawn by `draw`, repeats allowed. The picks are reserved up front so a count
+/// too large to ever reply with fails right away instead of drawing until memory runs out
+pub fn repeat<T>(count: usize, mut draw: impl FnMut() -> T) -> Option<Vec<T>> {
+    let mut picks = Vec::new();
+    picks.try_reserve_exact(count).ok()?;
+    picks.extend((0..count).map(|_| draw()));
+    Some(picks)
+}
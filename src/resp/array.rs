@@ -4,12 +4,16 @@ use bytes::BytesMut;
 
 use super::{
     int::{int, RespInt},
-    parse, Resp, RespConcreteType, RespError, RespTypePartialable,
+    parse_nested, Resp, RespConcreteType, RespError, RespLimits, RespTypePartialable,
 };
 
+// declared lengths are not trusted for preallocation beyond this many items
+const MAX_PREALLOCATED_ITEMS: usize = 1024;
+
 pub enum RespArray {
     Concrete(RespArrayConcrete),
     Partial(RespArrayPartial),
+    Null,
 }
 
 pub type RespArrayConcrete = VecDeque<RespConcreteType>;
@@ -19,16 +23,24 @@ pub struct RespArrayPartial {
     length: RespInt,
     concrete_array: Option<Vec<RespConcreteType>>,
     partial_item: Option<Box<RespTypePartialable>>,
+    // the prefix every item must start with, checked as each one starts arriving
+    item_type: Option<u8>,
 }
 
+// aggregates other than arrays (maps, sets, pushes, attributes) share the array framing,
+// maps and attributes declare the number of entries, each of which is `entry_width` items
 pub fn array_with_partial(
     buf: &mut BytesMut,
     partial: RespArrayPartial,
+    entry_width: usize,
+    limits: &RespLimits,
+    depth: usize,
 ) -> Result<RespArray, RespError> {
     let RespArrayPartial {
         length,
         concrete_array,
         partial_item,
+        item_type,
     } = partial;
 
     // length at this point could still be partial
@@ -36,13 +48,15 @@ pub fn array_with_partial(
         // if partial, try reading the rest of the length
         RespInt::Partial(partial) => match int(buf, Some(partial))? {
             // if concrete length received, then proceed to read array
-            RespInt::Concrete(i) => i,
+            RespInt::Concrete(-1) => return Ok(RespArray::Null),
+            RespInt::Concrete(i) => item_count(i, entry_width, limits, depth)?,
             // if partial length received, then return partial array with updated partial length
             RespInt::Partial(partial) => {
                 return Ok(RespArray::Partial(RespArrayPartial {
                     length: RespInt::Partial(partial),
                     concrete_array: None,
                     partial_item: None,
+                    item_type,
                 }))
             }
         },
@@ -52,11 +66,12 @@ pub fn array_with_partial(
 
     // length is ready, now read the array
     // look at the last item in the array, check if it is a partial
-    let mut concrete_array = concrete_array.unwrap_or(Vec::with_capacity(length as usize));
+    let mut concrete_array = concrete_array
+        .unwrap_or_else(|| Vec::with_capacity((length as usize).min(MAX_PREALLOCATED_ITEMS)));
 
     // if partial item exists, try to parse
     if let Some(partial) = partial_item {
-        match parse(buf, Some(*partial))? {
+        match parse_nested(buf, Some(*partial), limits, depth + 1)? {
             // if the parse result is concrete, then add to array and continue
             Resp::Concrete(r) => {
                 concrete_array.push(r);
@@ -67,6 +82,7 @@ pub fn array_with_partial(
                     length: RespInt::Concrete(length),
                     concrete_array: Some(concrete_array),
                     partial_item: Some(Box::new(partial)),
+                    item_type,
                 }))
             }
         }
@@ -76,6 +92,9 @@ pub fn array_with_partial(
         buf,
         concrete_array,
         TryInto::<usize>::try_into(length).map_err(|_| RespError::BadArraySize(length))?,
+        limits,
+        depth,
+        item_type,
     )
 }
 
@@ -83,9 +102,15 @@ fn array_with_length(
     buf: &mut BytesMut,
     mut concrete_array: Vec<RespConcreteType>,
     length: usize,
+    limits: &RespLimits,
+    depth: usize,
+    item_type: Option<u8>,
 ) -> Result<RespArray, RespError> {
     while concrete_array.len() < length && !buf.is_empty() {
-        match parse(buf, None)? {
+        if let Some(expected) = item_type.filter(|&expected| expected != buf[0]) {
+            return Err(RespError::UnexpectedItemType(expected, buf[0]));
+        }
+        match parse_nested(buf, None, limits, depth + 1)? {
             // if the parse result is concrete, then add to array and continue
             Resp::Concrete(r) => {
                 concrete_array.push(r);
@@ -96,6 +121,7 @@ fn array_with_length(
                     length: RespInt::Concrete(length as i64),
                     concrete_array: Some(concrete_array),
                     partial_item: Some(Box::new(partial)),
+                    item_type,
                 }))
             }
         }
@@ -108,13 +134,22 @@ fn array_with_length(
             length: RespInt::Concrete(length as i64),
             concrete_array: Some(concrete_array),
             partial_item: None,
+            item_type,
         })),
 
         false => Ok(RespArray::Concrete(VecDeque::from(concrete_array))),
     }
 }
 
-pub fn array(buf: &mut BytesMut) -> Result<RespArray, RespError> {
+/// Reads an aggregate's length and as many items as are available, `item_type` restricts
+/// the items to a single type like the bulk strings of a request
+pub fn array(
+    buf: &mut BytesMut,
+    entry_width: usize,
+    limits: &RespLimits,
+    depth: usize,
+    item_type: Option<u8>,
+) -> Result<RespArray, RespError> {
     // length at this point could still be partial
     let length = match int(buf, None)? {
         // if concrete length received, then proceed to read array
@@ -125,16 +160,48 @@ pub fn array(buf: &mut BytesMut) -> Result<RespArray, RespError> {
                 length: RespInt::Partial(partial),
                 concrete_array: None,
                 partial_item: None,
+                item_type,
             }))
         }
     };
 
+    // *-1 is the RESP2 null array
+    if length == -1 {
+        return Ok(RespArray::Null);
+    }
+
     // length is ready, now read the array
     // look at the last item in the array, check if it is a partial
-    let length = length
-        .try_into()
-        .map_err(|_| RespError::BadArraySize(length))?;
+    let length = item_count(length, entry_width, limits, depth)?;
 
-    let concrete_array = Vec::with_capacity(length);
-    array_with_length(buf, concrete_array, length)
+    let concrete_array = Vec::with_capacity((length as usize).min(MAX_PREALLOCATED_ITEMS));
+    array_with_length(
+        buf,
+        concrete_array,
+        length as usize,
+        limits,
+        depth,
+        item_type,
+    )
+}
+
+fn item_count(
+    length: i64,
+    entry_width: usize,
+    limits: &RespLimits,
+    depth: usize,
+) -> Result<i64, RespError> {
+    if length < 0 {
+        return Err(RespError::BadArraySize(length));
+    }
+    if length as u64 > limits.max_multibulk_len as u64 {
+        return Err(RespError::MultibulkTooLong(length));
+    }
+    // nested aggregates recurse through the parser, so their depth is bounded
+    if depth >= limits.max_nesting_depth {
+        return Err(RespError::NestingTooDeep(depth));
+    }
+    length
+        .checked_mul(entry_width as i64)
+        .ok_or(RespError::BadArraySize(length))
 }
@@ -0,0 +1,260 @@
+use bytes::{BufMut, BytesMut};
+
+use super::{RespConcreteType, RespVersion};
+
+/// Serializes a value into `buf`, downgrading RESP3 types to their RESP2 equivalents
+/// when the connection has not negotiated RESP3
+pub fn encode(value: &RespConcreteType, protocol: RespVersion, buf: &mut BytesMut) {
+    match value {
+        RespConcreteType::Array(items) => {
+            aggregate_header(buf, b'*', items.len());
+            for item in items {
+                encode(item, protocol, buf);
+            }
+        }
+        RespConcreteType::Int(i) => line(buf, b':', i.to_string().as_bytes()),
+        RespConcreteType::BulkString(string) => bulk(buf, b'$', string),
+        RespConcreteType::SimpleString(string) => line(buf, b'+', string.as_bytes()),
+        RespConcreteType::Error(error) => line(buf, b'-', error.as_bytes()),
+        RespConcreteType::NullBulkString | RespConcreteType::Null => null(buf, b'$', protocol),
+        RespConcreteType::NullArray => null(buf, b'*', protocol),
+        RespConcreteType::Map(pairs) | RespConcreteType::Attribute(pairs) => {
+            match (value, protocol) {
+                (RespConcreteType::Attribute(_), RespVersion::Resp3) => {
+                    aggregate_header(buf, b'|', pairs.len())
+                }
+                (_, RespVersion::Resp3) => aggregate_header(buf, b'%', pairs.len()),
+                // RESP2 has no maps, they are sent as a flat array of keys and values
+                (_, RespVersion::Resp2) => aggregate_header(buf, b'*', pairs.len() * 2),
+            }
+            for (key, value) in pairs {
+                encode(key, protocol, buf);
+                encode(value, protocol, buf);
+            }
+        }
+        RespConcreteType::Set(items) | RespConcreteType::Push(items) => {
+            let prefix = match (value, protocol) {
+                (_, RespVersion::Resp2) => b'*',
+                (RespConcreteType::Set(_), RespVersion::Resp3) => b'~',
+                _ => b'>',
+            };
+            aggregate_header(buf, prefix, items.len());
+            for item in items {
+                encode(item, protocol, buf);
+            }
+        }
+        RespConcreteType::Double(double) => match protocol {
+            RespVersion::Resp2 => bulk(buf, b'$', format_double(*double).as_bytes()),
+            RespVersion::Resp3 => line(buf, b',', format_double(*double).as_bytes()),
+        },
+        RespConcreteType::Boolean(boolean) => match protocol {
+            RespVersion::Resp2 => line(buf, b':', if *boolean { b"1" } else { b"0" }),
+            RespVersion::Resp3 => line(buf, b'#', if *boolean { b"t" } else { b"f" }),
+        },
+        RespConcreteType::BigNumber(number) => match protocol {
+            RespVersion::Resp2 => bulk(buf, b'$', number.as_bytes()),
+            RespVersion::Resp3 => line(buf, b'(', number.as_bytes()),
+        },
+        RespConcreteType::VerbatimString(verbatim) => match protocol {
+            RespVersion::Resp2 => bulk(buf, b'$', &verbatim.string),
+            RespVersion::Resp3 => {
+                let payload_len = verbatim.format.len() + 1 + verbatim.string.len();
+                line(buf, b'=', payload_len.to_string().as_bytes());
+                buf.put_slice(verbatim.format.as_bytes());
+                buf.put_u8(b':');
+                buf.put_slice(&verbatim.string);
+                buf.put_slice(b"\r\n");
+            }
+        },
+        RespConcreteType::BulkError(error) => match protocol {
+            RespVersion::Resp2 => {
+                // simple errors cannot contain line breaks
+                let error = String::from_utf8_lossy(error).replace(['\r', '\n'], " ");
+                line(buf, b'-', error.as_bytes())
+            }
+            RespVersion::Resp3 => bulk(buf, b'!', error),
+        },
+    }
+}
+
+fn line(buf: &mut BytesMut, prefix: u8, content: &[u8]) {
+    buf.put_u8(prefix);
+    buf.put_slice(content);
+    buf.put_slice(b"\r\n");
+}
+
+fn bulk(buf: &mut BytesMut, prefix: u8, content: &[u8]) {
+    line(buf, prefix, content.len().to_string().as_bytes());
+    buf.put_slice(content);
+    buf.put_slice(b"\r\n");
+}
+
+fn aggregate_header(buf: &mut BytesMut, prefix: u8, len: usize) {
+    line(buf, prefix, len.to_string().as_bytes());
+}
+
+fn null(buf: &mut BytesMut, resp2_prefix: u8, protocol: RespVersion) {
+    match protocol {
+        RespVersion::Resp2 => line(buf, resp2_prefix, b"-1"),
+        RespVersion::Resp3 => buf.put_slice(b"_\r\n"),
+    }
+}
+
+pub fn format_double(double: f64) -> String {
+    if double.is_nan() {
+        return "nan".to_string();
+    }
+    if double.is_infinite() {
+        return match double.is_sign_positive() {
+            true => "inf".to_string(),
+            false => "-inf".to_string(),
+        };
+    }
+
+    // plain notation for everyday magnitudes, exponent notation otherwise
+    let magnitude = double.abs();
+    if magnitude == 0.0 || (1e-5..1e17).contains(&magnitude) {
+        double.to_string()
+    } else {
+        format!("{double:e}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+
+    use super::{encode, format_double};
+    use crate::resp::{string::RespVerbatimStringConcrete, RespConcreteType, RespVersion};
+
+    fn encoded(value: RespConcreteType, protocol: RespVersion) -> BytesMut {
+        let mut out = BytesMut::new();
+        encode(&value, protocol, &mut out);
+        out
+    }
+
+    // the encoding of `value` for RESP2 and for RESP3
+    fn assert_encodes(value: impl Fn() -> RespConcreteType, resp2: &str, resp3: &str) {
+        assert_eq!(encoded(value(), RespVersion::Resp2), resp2.as_bytes());
+        assert_eq!(encoded(value(), RespVersion::Resp3), resp3.as_bytes());
+    }
+
+    fn pairs() -> RespConcreteType {
+        RespConcreteType::Map(vec![
+            (RespConcreteType::bulk("a"), RespConcreteType::Int(1)),
+            (RespConcreteType::bulk("b"), RespConcreteType::Null),
+        ])
+    }
+
+    #[test]
+    fn types_both_protocols_share() {
+        assert_encodes(RespConcreteType::ok, "+OK\r\n", "+OK\r\n");
+        assert_encodes(|| RespConcreteType::Int(-7), ":-7\r\n", ":-7\r\n");
+        assert_encodes(
+            || RespConcreteType::bulk("a\r\nb"),
+            "$4\r\na\r\nb\r\n",
+            "$4\r\na\r\nb\r\n",
+        );
+        assert_encodes(
+            || RespConcreteType::Error("ERR no".to_string()),
+            "-ERR no\r\n",
+            "-ERR no\r\n",
+        );
+        assert_encodes(
+            || RespConcreteType::array([RespConcreteType::bulk(""), RespConcreteType::Int(2)]),
+            "*2\r\n$0\r\n\r\n:2\r\n",
+            "*2\r\n$0\r\n\r\n:2\r\n",
+        );
+    }
+
+    #[test]
+    fn nulls_downgrade_to_their_resp2_kind() {
+        assert_encodes(|| RespConcreteType::NullBulkString, "$-1\r\n", "_\r\n");
+        assert_encodes(|| RespConcreteType::NullArray, "*-1\r\n", "_\r\n");
+        assert_encodes(|| RespConcreteType::Null, "$-1\r\n", "_\r\n");
+    }
+
+    #[test]
+    fn aggregates_downgrade_to_arrays() {
+        assert_encodes(
+            pairs,
+            "*4\r\n$1\r\na\r\n:1\r\n$1\r\nb\r\n$-1\r\n",
+            "%2\r\n$1\r\na\r\n:1\r\n$1\r\nb\r\n_\r\n",
+        );
+        assert_encodes(
+            || RespConcreteType::Set([RespConcreteType::bulk("m")].into()),
+            "*1\r\n$1\r\nm\r\n",
+            "~1\r\n$1\r\nm\r\n",
+        );
+        assert_encodes(
+            || RespConcreteType::Push([RespConcreteType::bulk("message")].into()),
+            "*1\r\n$7\r\nmessage\r\n",
+            ">1\r\n$7\r\nmessage\r\n",
+        );
+        assert_encodes(
+            || {
+                RespConcreteType::Attribute(vec![(
+                    RespConcreteType::simple("ttl"),
+                    RespConcreteType::Int(1),
+                )])
+            },
+            "*2\r\n+ttl\r\n:1\r\n",
+            "|1\r\n+ttl\r\n:1\r\n",
+        );
+        // nested values are downgraded too
+        assert_encodes(
+            || RespConcreteType::array([pairs()]),
+            "*1\r\n*4\r\n$1\r\na\r\n:1\r\n$1\r\nb\r\n$-1\r\n",
+            "*1\r\n%2\r\n$1\r\na\r\n:1\r\n$1\r\nb\r\n_\r\n",
+        );
+    }
+
+    #[test]
+    fn scalars_downgrade_to_strings_and_integers() {
+        assert_encodes(
+            || RespConcreteType::Double(1.5),
+            "$3\r\n1.5\r\n",
+            ",1.5\r\n",
+        );
+        assert_encodes(
+            || RespConcreteType::Double(f64::INFINITY),
+            "$3\r\ninf\r\n",
+            ",inf\r\n",
+        );
+        assert_encodes(|| RespConcreteType::Boolean(true), ":1\r\n", "#t\r\n");
+        assert_encodes(|| RespConcreteType::Boolean(false), ":0\r\n", "#f\r\n");
+        assert_encodes(
+            || RespConcreteType::BigNumber("-123456789012345678901".to_string()),
+            "$22\r\n-123456789012345678901\r\n",
+            "(-123456789012345678901\r\n",
+        );
+        assert_encodes(
+            || {
+                RespConcreteType::VerbatimString(RespVerbatimStringConcrete {
+                    format: "txt".to_string(),
+                    string: Bytes::from("a\r\nb"),
+                })
+            },
+            "$4\r\na\r\nb\r\n",
+            "=8\r\ntxt:a\r\nb\r\n",
+        );
+        // a simple error can't hold line breaks
+        assert_encodes(
+            || RespConcreteType::BulkError(Bytes::from("ERR a\r\nb")),
+            "-ERR a  b\r\n",
+            "!8\r\nERR a\r\nb\r\n",
+        );
+    }
+
+    #[test]
+    fn doubles() {
+        assert_eq!(format_double(0.0), "0");
+        assert_eq!(format_double(-2.5), "-2.5");
+        assert_eq!(format_double(1e16), "10000000000000000");
+        assert_eq!(format_double(1e17), "1e17");
+        assert_eq!(format_double(0.00001), "0.00001");
+        assert_eq!(format_double(0.000001), "1e-6");
+        assert_eq!(format_double(f64::NEG_INFINITY), "-inf");
+        assert_eq!(format_double(f64::NAN), "nan");
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use super::{array::RespArrayConcrete, RespConcreteType, RespError, RespLimits};
+
+#[derive(Debug)]
+pub enum RespInline {
+    Concrete(RespArrayConcrete),
+    Partial(RespInlinePartial),
+}
+
+#[derive(Debug)]
+pub struct RespInlinePartial(Bytes);
+
+// inline commands are plain lines of space separated arguments, as typed into telnet or nc
+pub fn inline(
+    buf: &mut BytesMut,
+    partial: Option<RespInlinePartial>,
+    limits: &RespLimits,
+) -> Result<RespInline, RespError> {
+    let RespInlinePartial(partial_line) = partial.unwrap_or(RespInlinePartial(Bytes::new()));
+
+    // unlike the rest of the protocol, a bare \n terminates an inline line
+    let Some(i) = buf.iter().position(|b| *b == b'\n') else {
+        if partial_line.len() + buf.len() > limits.max_inline_len {
+            return Err(RespError::InlineTooLong);
+        }
+        let line = Bytes::from([partial_line.as_ref(), buf.split().as_ref()].concat());
+        return Ok(RespInline::Partial(RespInlinePartial(line)));
+    };
+
+    if partial_line.len() + i > limits.max_inline_len {
+        return Err(RespError::InlineTooLong);
+    }
+    let mut line = [partial_line.as_ref(), buf.split_to(i).as_ref()].concat();
+    buf.advance(1);
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+
+    // an empty line comes out as an empty command, skipped like an empty multibulk, so
+    // whatever follows it is told apart as inline or multibulk afresh
+    Ok(RespInline::Concrete(split_args(&line)?))
+}
+
+// splits a line the way redis-cli does, honouring double quotes with escapes and single quotes
+fn split_args(line: &[u8]) -> Result<RespArrayConcrete, RespError> {
+    let mut args = VecDeque::new();
+    let mut i = 0;
+
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == line.len() {
+            return Ok(args);
+        }
+
+        let mut arg = Vec::new();
+        while i < line.len() && !line[i].is_ascii_whitespace() {
+            match line[i] {
+                b'"' => {
+                    i += 1;
+                    loop {
+                        match line.get(i) {
+                            None => return Err(RespError::UnbalancedQuotes),
+                            Some(b'"') => break,
+                            Some(b'\\') if i + 3 < line.len() && line[i + 1] == b'x' => {
+                                match (hex_digit(line[i + 2]), hex_digit(line[i + 3])) {
+                                    (Some(hi), Some(lo)) => {
+                                        arg.push(hi << 4 | lo);
+                                        i += 3;
+                                    }
+                                    _ => {
+                                        arg.push(b'x');
+                                        i += 1;
+                                    }
+                                }
+                            }
+                            Some(b'\\') if i + 1 < line.len() => {
+                                i += 1;
+                                arg.push(match line[i] {
+                                    b'n' => b'\n',
+                                    b'r' => b'\r',
+                                    b't' => b'\t',
+                                    b'b' => 0x08,
+                                    b'a' => 0x07,
+                                    b => b,
+                                });
+                            }
+                            Some(b) => arg.push(*b),
+                        }
+                        i += 1;
+                    }
+                    // a closing quote must be followed by a space or the end of the line
+                    i += 1;
+                    if i < line.len() && !line[i].is_ascii_whitespace() {
+                        return Err(RespError::UnbalancedQuotes);
+                    }
+                }
+                b'\'' => {
+                    i += 1;
+                    loop {
+                        match line.get(i) {
+                            None => return Err(RespError::UnbalancedQuotes),
+                            Some(b'\'') => break,
+                            Some(b'\\') if line.get(i + 1) == Some(&b'\'') => {
+                                arg.push(b'\'');
+                                i += 1;
+                            }
+                            Some(b) => arg.push(*b),
+                        }
+                        i += 1;
+                    }
+                    i += 1;
+                    if i < line.len() && !line[i].is_ascii_whitespace() {
+                        return Err(RespError::UnbalancedQuotes);
+                    }
+                }
+                b => {
+                    arg.push(b);
+                    i += 1;
+                }
+            }
+        }
+
+        args.push_back(RespConcreteType::BulkString(Bytes::from(arg)));
+    }
+}
+
+fn hex_digit(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+
+    use crate::resp::{parse_request, Resp, RespConcreteType, RespError, RespLimits};
+
+    fn args(resp: Resp) -> Vec<Bytes> {
+        match resp {
+            Resp::Concrete(RespConcreteType::Array(args)) => args
+                .into_iter()
+                .map(|arg| match arg {
+                    RespConcreteType::BulkString(arg) => arg,
+                    arg => panic!("not a bulk string: {arg:?}"),
+                })
+                .collect(),
+            resp => panic!("not a complete request: {resp:?}"),
+        }
+    }
+
+    fn request(input: &[u8]) -> Result<Vec<Bytes>, RespError> {
+        parse_request(&mut BytesMut::from(input), None, &RespLimits::default()).map(args)
+    }
+
+    #[test]
+    fn splits_on_spaces() {
+        assert_eq!(
+            request(b"SET  key value\r\n").unwrap(),
+            ["SET", "key", "value"]
+        );
+        assert_eq!(request(b"PING\n").unwrap(), ["PING"]);
+    }
+
+    #[test]
+    fn quotes_and_escapes() {
+        assert_eq!(
+            request(b"SET \"a b\" 'c d'\r\n").unwrap(),
+            ["SET", "a b", "c d"]
+        );
+        assert_eq!(request(b"ECHO \"\\x41\\n\"\r\n").unwrap(), ["ECHO", "A\n"]);
+        assert_eq!(request(b"ECHO 'it\\'s'\r\n").unwrap(), ["ECHO", "it's"]);
+        assert!(matches!(
+            request(b"ECHO \"open\r\n"),
+            Err(RespError::UnbalancedQuotes)
+        ));
+        assert!(matches!(
+            request(b"ECHO \"a\"b\r\n"),
+            Err(RespError::UnbalancedQuotes)
+        ));
+    }
+
+    #[test]
+    fn line_split_across_reads() {
+        let limits = RespLimits::default();
+        let mut buf = BytesMut::from(&b"ECHO hel"[..]);
+        let Resp::Partial(partial) = parse_request(&mut buf, None, &limits).unwrap() else {
+            panic!("a line without its end is partial");
+        };
+        buf.extend_from_slice(b"lo\r\n");
+        let resp = parse_request(&mut buf, Some(partial), &limits).unwrap();
+        assert_eq!(args(resp), ["ECHO", "hello"]);
+    }
+
+    #[test]
+    fn blank_line_leaves_no_partial_state() {
+        let limits = RespLimits::default();
+        let mut buf = BytesMut::from(&b"\r\n"[..]);
+        assert!(args(parse_request(&mut buf, None, &limits).unwrap()).is_empty());
+        assert!(buf.is_empty());
+
+        // the next read is a multibulk, not the rest of an inline line
+        buf.extend_from_slice(b"*1\r\n$4\r\nPING\r\n");
+        assert_eq!(
+            args(parse_request(&mut buf, None, &limits).unwrap()),
+            ["PING"]
+        );
+    }
+
+    #[test]
+    fn too_long() {
+        let limits = RespLimits {
+            max_inline_len: 8,
+            ..RespLimits::default()
+        };
+        let mut buf = BytesMut::from(&b"ECHO 123456789"[..]);
+        assert!(matches!(
+            parse_request(&mut buf, None, &limits),
+            Err(RespError::InlineTooLong)
+        ));
+    }
+}
@@ -14,6 +14,9 @@ pub struct RespIntPartial(Bytes);
 
 pub type RespIntConcrete = i64;
 
+// no valid i64 needs more characters than this, longer partial lines are rejected early
+const MAX_INT_LEN: usize = 20;
+
 impl RespInt {
     fn from_partial(partial_int: Bytes) -> RespInt {
         RespInt::Partial(RespIntPartial(partial_int))
@@ -37,6 +40,9 @@ pub fn int(buf: &mut BytesMut, partial: Option<RespIntPartial>) -> Result<RespIn
         // return partial
         Word::Partial(word) => {
             let concatenated_bytes = Bytes::from([partial_bytes.as_ref(), word.as_ref()].concat());
+            if concatenated_bytes.len() > MAX_INT_LEN {
+                return Err(RespError::IntParseFailure);
+            }
             Ok(RespInt::from_partial(concatenated_bytes))
         }
     }
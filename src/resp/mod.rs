@@ -1,24 +1,90 @@
 use bytes::{Buf, Bytes, BytesMut};
+use thiserror::Error;
 
 use self::{
     array::{array, array_with_partial, RespArray, RespArrayConcrete, RespArrayPartial},
+    inline::{inline, RespInline, RespInlinePartial},
     int::{int, RespInt, RespIntConcrete, RespIntPartial},
-    string::{string, string_with_partial, RespString, RespBulkStringConcrete, RespBulkStringPartial},
+    simple::{simple_string, RespSimpleString, RespSimpleStringConcrete, RespSimpleStringPartial},
+    string::{
+        string, string_with_partial, verbatim_string, RespBulkStringConcrete,
+        RespBulkStringPartial, RespString, RespVerbatimStringConcrete,
+    },
 };
 
 pub mod array;
+pub mod encode;
+pub mod inline;
 pub mod int;
+pub mod simple;
 pub mod string;
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum RespError {
+    #[error("invalid simple string")]
     StringParseFailure,
     // UnexpectedEnd,
+    #[error("unknown starting byte {0:#04x}")]
     UnknownStartingByte(u8),
     // IOError(std::io::Error),
+    #[error("invalid integer")]
     IntParseFailure,
+    #[error("invalid double")]
+    DoubleParseFailure,
+    #[error("invalid boolean")]
+    BooleanParseFailure,
+    #[error("invalid big number")]
+    BigNumberParseFailure,
+    #[error("invalid null")]
+    NullParseFailure,
+    #[error("invalid verbatim string")]
+    VerbatimStringParseFailure,
+    #[error("unbalanced quotes in request")]
+    UnbalancedQuotes,
+    #[error("invalid bulk length {0}")]
     BadBulkStringSize(i64),
+    #[error("invalid multibulk length {0}")]
     BadArraySize(i64),
+    #[error("bulk length {0} exceeds proto-max-bulk-len")]
+    BulkTooLong(i64),
+    #[error("multibulk length {0} exceeds proto-max-multibulk-len")]
+    MultibulkTooLong(i64),
+    #[error("nesting depth {0} exceeds proto-max-nesting")]
+    NestingTooDeep(usize),
+    #[error("too big inline request")]
+    InlineTooLong,
+    #[error("too big query buffer")]
+    QueryBufferLimit,
+    #[error("expected '{}', got '{}'", *.0 as char, *.1 as char)]
+    UnexpectedItemType(u8, u8),
+}
+
+/// Upper bounds on what a peer may declare, so lengths coming off the wire
+/// cannot force huge allocations or unbounded recursion
+#[derive(Debug, Clone)]
+pub struct RespLimits {
+    pub max_bulk_len: usize,
+    pub max_multibulk_len: usize,
+    pub max_nesting_depth: usize,
+    pub max_inline_len: usize,
+}
+
+impl Default for RespLimits {
+    fn default() -> Self {
+        RespLimits {
+            max_bulk_len: 512 * 1024 * 1024,
+            max_multibulk_len: 1024 * 1024,
+            max_nesting_depth: 128,
+            max_inline_len: 64 * 1024,
+        }
+    }
+}
+
+/// Protocol version negotiated by a connection through `HELLO`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RespVersion {
+    Resp2,
+    Resp3,
 }
 
 #[derive(Debug)]
@@ -27,12 +93,46 @@ pub enum Resp {
     Partial(RespTypePartialable),
 }
 
-#[derive(Debug)]
+pub type RespMapConcrete = Vec<(RespConcreteType, RespConcreteType)>;
 
+#[derive(Debug)]
 pub enum RespConcreteType {
     Array(RespArrayConcrete),
     Int(RespIntConcrete),
     BulkString(RespBulkStringConcrete),
+    SimpleString(RespSimpleStringConcrete),
+    Error(RespSimpleStringConcrete),
+    NullBulkString,
+    NullArray,
+    // RESP3
+    Map(RespMapConcrete),
+    Set(RespArrayConcrete),
+    Double(f64),
+    Boolean(bool),
+    BigNumber(String),
+    VerbatimString(RespVerbatimStringConcrete),
+    BulkError(RespBulkStringConcrete),
+    Null,
+    Push(RespArrayConcrete),
+    Attribute(RespMapConcrete),
+}
+
+impl RespConcreteType {
+    pub fn ok() -> RespConcreteType {
+        RespConcreteType::SimpleString("OK".to_string())
+    }
+
+    pub fn simple(string: &str) -> RespConcreteType {
+        RespConcreteType::SimpleString(string.to_string())
+    }
+
+    pub fn bulk(string: impl Into<Bytes>) -> RespConcreteType {
+        RespConcreteType::BulkString(string.into())
+    }
+
+    pub fn array(items: impl IntoIterator<Item = RespConcreteType>) -> RespConcreteType {
+        RespConcreteType::Array(items.into_iter().collect())
+    }
 }
 
 #[derive(Debug)]
@@ -40,6 +140,21 @@ pub enum RespTypePartialable {
     Array(RespArrayPartial),
     Int(RespIntPartial),
     BulkString(RespBulkStringPartial),
+    SimpleString(RespSimpleStringPartial),
+    Error(RespSimpleStringPartial),
+    // RESP3
+    Map(RespArrayPartial),
+    Set(RespArrayPartial),
+    Double(RespSimpleStringPartial),
+    Boolean(RespSimpleStringPartial),
+    BigNumber(RespSimpleStringPartial),
+    VerbatimString(RespBulkStringPartial),
+    BulkError(RespBulkStringPartial),
+    Null(RespSimpleStringPartial),
+    Push(RespArrayPartial),
+    Attribute(RespArrayPartial),
+    // inline commands
+    Inline(RespInlinePartial),
 }
 
 #[derive(Debug)]
@@ -49,73 +164,638 @@ pub enum Word {
 }
 
 pub fn word(buf: &mut BytesMut) -> Word {
-    for (i, b) in buf.iter().enumerate() {
-        if *b == b'\r' {
+    match buf.windows(2).position(|w| w == b"\r\n") {
+        Some(i) => {
             let output = buf.split_to(i);
-            if 1 == buf.remaining() {
-                // edge case when \r was read but not \n
-                buf.clear();
-                return Word::Concrete(output.into());
-            }
             buf.advance(2);
-            return Word::Concrete(output.into());
+            Word::Concrete(output.into())
+        }
+        None => {
+            // a trailing \r stays in the buffer, its \n may arrive with the next read
+            let end = match buf.last() {
+                Some(b'\r') => buf.len() - 1,
+                _ => buf.len(),
+            };
+            Word::Partial(buf.split_to(end).into())
         }
+    }
+}
 
-        if *b == b'\n' {
-            let output = Bytes::new();
+/// Parses a client request, which is either a multibulk array or an inline command
+pub fn parse_request(
+    buf: &mut BytesMut,
+    partial: Option<RespTypePartialable>,
+    limits: &RespLimits,
+) -> Result<Resp, RespError> {
+    let request = match partial {
+        // commands are only ever sent as arrays of bulk strings, anything else is refused
+        // as soon as it starts rather than once a whole nested value has been read
+        None if buf.first() == Some(&b'*') => {
             buf.advance(1);
-            return Word::Partial(output);
+            aggregate(array(buf, 1, limits, 0, Some(b'$'))?, Aggregate::Array)?
+        }
+        None => inline_request(inline(buf, None, limits)?)?,
+        partial => parse(buf, partial, limits)?,
+    };
+
+    // nor can an argument be a null bulk string
+    if let Resp::Concrete(RespConcreteType::Array(ref args)) = request {
+        if args
+            .iter()
+            .any(|arg| matches!(arg, RespConcreteType::NullBulkString))
+        {
+            return Err(RespError::BadBulkStringSize(-1));
         }
     }
-    Word::Partial(buf.split().into())
+
+    Ok(request)
+}
+
+fn inline_request(result: RespInline) -> Result<Resp, RespError> {
+    match result {
+        RespInline::Concrete(r) => Ok(Resp::Concrete(RespConcreteType::Array(r))),
+        RespInline::Partial(partial) => Ok(Resp::Partial(RespTypePartialable::Inline(partial))),
+    }
+}
+
+pub fn parse(
+    buf: &mut BytesMut,
+    partial: Option<RespTypePartialable>,
+    limits: &RespLimits,
+) -> Result<Resp, RespError> {
+    parse_nested(buf, partial, limits, 0)
 }
 
-pub fn parse(buf: &mut BytesMut, partial: Option<RespTypePartialable>) -> Result<Resp, RespError> {
+// `depth` counts the aggregates enclosing the value being parsed
+fn parse_nested(
+    buf: &mut BytesMut,
+    partial: Option<RespTypePartialable>,
+    limits: &RespLimits,
+    depth: usize,
+) -> Result<Resp, RespError> {
     match partial {
         Some(partial) => match partial {
-            RespTypePartialable::Array(partial_array) => {
-                match array_with_partial(buf, partial_array)? {
-                    RespArray::Concrete(r) => Ok(Resp::Concrete(RespConcreteType::Array(r))),
-                    RespArray::Partial(partial) => {
-                        Ok(Resp::Partial(RespTypePartialable::Array(partial)))
-                    }
-                }
-            }
+            RespTypePartialable::Array(partial_array) => aggregate(
+                array_with_partial(buf, partial_array, 1, limits, depth)?,
+                Aggregate::Array,
+            ),
+            RespTypePartialable::Map(partial_map) => aggregate(
+                array_with_partial(buf, partial_map, 2, limits, depth)?,
+                Aggregate::Map,
+            ),
+            RespTypePartialable::Set(partial_set) => aggregate(
+                array_with_partial(buf, partial_set, 1, limits, depth)?,
+                Aggregate::Set,
+            ),
+            RespTypePartialable::Push(partial_push) => aggregate(
+                array_with_partial(buf, partial_push, 1, limits, depth)?,
+                Aggregate::Push,
+            ),
+            RespTypePartialable::Attribute(partial_attribute) => aggregate(
+                array_with_partial(buf, partial_attribute, 2, limits, depth)?,
+                Aggregate::Attribute,
+            ),
             RespTypePartialable::Int(partial_int) => match int(buf, Some(partial_int))? {
                 RespInt::Concrete(r) => Ok(Resp::Concrete(RespConcreteType::Int(r))),
                 RespInt::Partial(partial) => Ok(Resp::Partial(RespTypePartialable::Int(partial))),
             },
-            RespTypePartialable::BulkString(partial_string) => {
-                match string_with_partial(buf, partial_string)? {
-                    RespString::Concrete(r) => Ok(Resp::Concrete(RespConcreteType::BulkString(r))),
-                    RespString::Partial(partial) => {
-                        Ok(Resp::Partial(RespTypePartialable::BulkString(partial)))
-                    }
-                }
+            RespTypePartialable::BulkString(partial_string) => blob(
+                string_with_partial(buf, partial_string, limits)?,
+                Blob::BulkString,
+            ),
+            RespTypePartialable::VerbatimString(partial_string) => blob(
+                string_with_partial(buf, partial_string, limits)?,
+                Blob::VerbatimString,
+            ),
+            RespTypePartialable::BulkError(partial_string) => blob(
+                string_with_partial(buf, partial_string, limits)?,
+                Blob::BulkError,
+            ),
+            RespTypePartialable::SimpleString(partial_line) => {
+                line(simple_string(buf, Some(partial_line))?, Line::SimpleString)
+            }
+            RespTypePartialable::Error(partial_line) => {
+                line(simple_string(buf, Some(partial_line))?, Line::Error)
+            }
+            RespTypePartialable::Double(partial_line) => {
+                line(simple_string(buf, Some(partial_line))?, Line::Double)
+            }
+            RespTypePartialable::Boolean(partial_line) => {
+                line(simple_string(buf, Some(partial_line))?, Line::Boolean)
+            }
+            RespTypePartialable::BigNumber(partial_line) => {
+                line(simple_string(buf, Some(partial_line))?, Line::BigNumber)
+            }
+            RespTypePartialable::Null(partial_line) => {
+                line(simple_string(buf, Some(partial_line))?, Line::Null)
+            }
+            RespTypePartialable::Inline(partial_line) => {
+                inline_request(inline(buf, Some(partial_line), limits)?)
             }
         },
         None => match buf.get_u8() {
-            b'*' => match array(buf)? {
-                RespArray::Concrete(r) => Ok(Resp::Concrete(RespConcreteType::Array(r))),
-                RespArray::Partial(partial) => {
-                    Ok(Resp::Partial(RespTypePartialable::Array(partial)))
-                }
-            },
+            b'*' => aggregate(array(buf, 1, limits, depth, None)?, Aggregate::Array),
+            b'%' => aggregate(array(buf, 2, limits, depth, None)?, Aggregate::Map),
+            b'~' => aggregate(array(buf, 1, limits, depth, None)?, Aggregate::Set),
+            b'>' => aggregate(array(buf, 1, limits, depth, None)?, Aggregate::Push),
+            b'|' => aggregate(array(buf, 2, limits, depth, None)?, Aggregate::Attribute),
             b':' => match int(buf, None)? {
                 RespInt::Concrete(r) => Ok(Resp::Concrete(RespConcreteType::Int(r))),
                 RespInt::Partial(partial) => Ok(Resp::Partial(RespTypePartialable::Int(partial))),
             },
-            b'$' => match string(buf)? {
-                RespString::Concrete(r) => Ok(Resp::Concrete(RespConcreteType::BulkString(r))),
-                RespString::Partial(partial) => {
-                    Ok(Resp::Partial(RespTypePartialable::BulkString(partial)))
+            b'$' => blob(string(buf, limits)?, Blob::BulkString),
+            b'=' => blob(string(buf, limits)?, Blob::VerbatimString),
+            b'!' => blob(string(buf, limits)?, Blob::BulkError),
+            b'+' => line(simple_string(buf, None)?, Line::SimpleString),
+            b'-' => line(simple_string(buf, None)?, Line::Error),
+            b',' => line(simple_string(buf, None)?, Line::Double),
+            b'#' => line(simple_string(buf, None)?, Line::Boolean),
+            b'(' => line(simple_string(buf, None)?, Line::BigNumber),
+            b'_' => line(simple_string(buf, None)?, Line::Null),
+            b => Err(RespError::UnknownStartingByte(b)),
+        },
+    }
+}
+
+enum Aggregate {
+    Array,
+    Map,
+    Set,
+    Push,
+    Attribute,
+}
+
+fn aggregate(result: RespArray, kind: Aggregate) -> Result<Resp, RespError> {
+    match result {
+        RespArray::Concrete(r) => Ok(Resp::Concrete(match kind {
+            Aggregate::Array => RespConcreteType::Array(r),
+            Aggregate::Map => RespConcreteType::Map(pairs(r)),
+            Aggregate::Set => RespConcreteType::Set(r),
+            Aggregate::Push => RespConcreteType::Push(r),
+            Aggregate::Attribute => RespConcreteType::Attribute(pairs(r)),
+        })),
+        RespArray::Partial(partial) => Ok(Resp::Partial(match kind {
+            Aggregate::Array => RespTypePartialable::Array(partial),
+            Aggregate::Map => RespTypePartialable::Map(partial),
+            Aggregate::Set => RespTypePartialable::Set(partial),
+            Aggregate::Push => RespTypePartialable::Push(partial),
+            Aggregate::Attribute => RespTypePartialable::Attribute(partial),
+        })),
+        RespArray::Null => Ok(Resp::Concrete(match kind {
+            Aggregate::Array => RespConcreteType::NullArray,
+            _ => RespConcreteType::Null,
+        })),
+    }
+}
+
+// maps and attributes are read as a flat array of alternating keys and values
+fn pairs(items: RespArrayConcrete) -> RespMapConcrete {
+    let mut items = items.into_iter();
+    let mut pairs = Vec::with_capacity(items.len() / 2);
+    while let (Some(key), Some(value)) = (items.next(), items.next()) {
+        pairs.push((key, value));
+    }
+    pairs
+}
+
+enum Blob {
+    BulkString,
+    VerbatimString,
+    BulkError,
+}
+
+fn blob(result: RespString, kind: Blob) -> Result<Resp, RespError> {
+    match result {
+        RespString::Concrete(r) => Ok(Resp::Concrete(match kind {
+            Blob::BulkString => RespConcreteType::BulkString(r),
+            Blob::VerbatimString => RespConcreteType::VerbatimString(verbatim_string(r)?),
+            Blob::BulkError => RespConcreteType::BulkError(r),
+        })),
+        RespString::Partial(partial) => Ok(Resp::Partial(match kind {
+            Blob::BulkString => RespTypePartialable::BulkString(partial),
+            Blob::VerbatimString => RespTypePartialable::VerbatimString(partial),
+            Blob::BulkError => RespTypePartialable::BulkError(partial),
+        })),
+        RespString::Null => Ok(Resp::Concrete(match kind {
+            Blob::BulkString => RespConcreteType::NullBulkString,
+            _ => RespConcreteType::Null,
+        })),
+    }
+}
+
+enum Line {
+    SimpleString,
+    Error,
+    Double,
+    Boolean,
+    BigNumber,
+    Null,
+}
+
+fn line(result: RespSimpleString, kind: Line) -> Result<Resp, RespError> {
+    match result {
+        RespSimpleString::Concrete(r) => Ok(Resp::Concrete(match kind {
+            Line::SimpleString => RespConcreteType::SimpleString(r),
+            Line::Error => RespConcreteType::Error(r),
+            Line::Double => RespConcreteType::Double(
+                r.parse::<f64>()
+                    .map_err(|_| RespError::DoubleParseFailure)?,
+            ),
+            Line::Boolean => match r.as_str() {
+                "t" => RespConcreteType::Boolean(true),
+                "f" => RespConcreteType::Boolean(false),
+                _ => return Err(RespError::BooleanParseFailure),
+            },
+            Line::BigNumber => {
+                let digits = r.strip_prefix(['-', '+']).unwrap_or(&r);
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(RespError::BigNumberParseFailure);
                 }
+                RespConcreteType::BigNumber(r)
+            }
+            Line::Null => match r.is_empty() {
+                true => RespConcreteType::Null,
+                false => return Err(RespError::NullParseFailure),
             },
-            b'\n' => {
-                buf.advance(1);
-                parse(buf, None)
+        })),
+        RespSimpleString::Partial(partial) => Ok(Resp::Partial(match kind {
+            Line::SimpleString => RespTypePartialable::SimpleString(partial),
+            Line::Error => RespTypePartialable::Error(partial),
+            Line::Double => RespTypePartialable::Double(partial),
+            Line::Boolean => RespTypePartialable::Boolean(partial),
+            Line::BigNumber => RespTypePartialable::BigNumber(partial),
+            Line::Null => RespTypePartialable::Null(partial),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::{
+        encode::encode, parse, parse_request, Resp, RespConcreteType, RespError, RespLimits,
+        RespTypePartialable, RespVersion,
+    };
+
+    fn request(input: &[u8], limits: &RespLimits) -> Result<Resp, RespError> {
+        parse_request(&mut BytesMut::from(input), None, limits)
+    }
+
+    #[test]
+    fn bulk_and_multibulk_limits() {
+        let limits = RespLimits {
+            max_bulk_len: 4,
+            max_multibulk_len: 2,
+            ..RespLimits::default()
+        };
+        assert!(request(b"*2\r\n$4\r\nECHO\r\n$1\r\na\r\n", &limits).is_ok());
+        assert!(matches!(
+            request(b"*1\r\n$5\r\n", &limits),
+            Err(RespError::BulkTooLong(5))
+        ));
+        assert!(matches!(
+            request(b"*3\r\n", &limits),
+            Err(RespError::MultibulkTooLong(3))
+        ));
+        assert!(matches!(
+            request(b"*-2\r\n", &limits),
+            Err(RespError::BadArraySize(-2))
+        ));
+        assert!(matches!(
+            request(b"*1\r\n$-2\r\n", &limits),
+            Err(RespError::BadBulkStringSize(-2))
+        ));
+    }
+
+    #[test]
+    fn request_items_must_be_bulk_strings() {
+        let limits = RespLimits::default();
+        // refused at the first byte of the item, before any of it is read
+        assert!(matches!(
+            request(b"*2\r\n$4\r\nECHO\r\n%1048576\r\n", &limits),
+            Err(RespError::UnexpectedItemType(b'$', b'%'))
+        ));
+        assert!(matches!(
+            request(b"*1\r\n:1\r\n", &limits),
+            Err(RespError::UnexpectedItemType(b'$', b':'))
+        ));
+        assert!(matches!(
+            request(b"*1\r\n$-1\r\n", &limits),
+            Err(RespError::BadBulkStringSize(-1))
+        ));
+    }
+
+    #[test]
+    fn request_item_type_is_checked_after_a_split() {
+        let limits = RespLimits::default();
+        let mut buf = BytesMut::from(&b"*2\r\n$4\r\nECHO\r\n"[..]);
+        let Ok(Resp::Partial(partial)) = parse_request(&mut buf, None, &limits) else {
+            panic!("the second item is still to come");
+        };
+        buf.extend_from_slice(b"~1\r\n");
+        assert!(matches!(
+            parse_request(&mut buf, Some(partial), &limits),
+            Err(RespError::UnexpectedItemType(b'$', b'~'))
+        ));
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let limits = RespLimits {
+            max_nesting_depth: 3,
+            ..RespLimits::default()
+        };
+        assert!(parse(
+            &mut BytesMut::from(&b"*1\r\n*1\r\n*1\r\n:1\r\n"[..]),
+            None,
+            &limits
+        )
+        .is_ok());
+        assert!(matches!(
+            parse(
+                &mut BytesMut::from(&b"*1\r\n*1\r\n*1\r\n*1\r\n"[..]),
+                None,
+                &limits
+            ),
+            Err(RespError::NestingTooDeep(3))
+        ));
+    }
+
+    // feeds `chunks` one read at a time, collecting every request parsed along the way
+    fn requests(chunks: &[&[u8]]) -> Vec<Vec<Vec<u8>>> {
+        let limits = RespLimits::default();
+        let (mut buf, mut partial) = (BytesMut::new(), None::<RespTypePartialable>);
+        let mut parsed = vec![];
+        for chunk in chunks {
+            buf.extend_from_slice(chunk);
+            while !buf.is_empty() {
+                match parse_request(&mut buf, partial.take(), &limits).unwrap() {
+                    Resp::Partial(rest) => {
+                        partial = Some(rest);
+                        break;
+                    }
+                    Resp::Concrete(RespConcreteType::Array(args)) => parsed.push(
+                        args.into_iter()
+                            .map(|arg| match arg {
+                                RespConcreteType::BulkString(arg) => arg.to_vec(),
+                                arg => panic!("not a bulk string: {arg:?}"),
+                            })
+                            .collect(),
+                    ),
+                    resp => panic!("not a request: {resp:?}"),
+                }
             }
-            b => Err(RespError::UnknownStartingByte(b)),
-        },
+        }
+        assert!(partial.is_none(), "a request was left unfinished");
+        parsed
+    }
+
+    #[test]
+    fn bulk_strings_are_read_by_length() {
+        let input = b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$6\r\na\r\n\r\nb\r\n";
+        let expected = vec![vec![b"SET".to_vec(), b"k".to_vec(), b"a\r\n\r\nb".to_vec()]];
+        assert_eq!(requests(&[input]), expected);
+
+        // wherever the read boundary falls, even inside the length or the \r\n
+        for split in 1..input.len() {
+            let (first, second) = input.split_at(split);
+            assert_eq!(requests(&[first, second]), expected, "split at {split}");
+        }
+        let bytes: Vec<&[u8]> = input.chunks(1).collect();
+        assert_eq!(requests(&bytes), expected);
+    }
+
+    #[test]
+    fn pipelined_requests() {
+        let input = b"*1\r\n$4\r\nPING\r\n*2\r\n$4\r\nECHO\r\n$0\r\n\r\nPING\r\n";
+        let expected = vec![
+            vec![b"PING".to_vec()],
+            vec![b"ECHO".to_vec(), b"".to_vec()],
+            vec![b"PING".to_vec()],
+        ];
+        assert_eq!(requests(&[input]), expected);
+        for split in 1..input.len() {
+            let (first, second) = input.split_at(split);
+            assert_eq!(requests(&[first, second]), expected, "split at {split}");
+        }
+    }
+
+    // feeds `chunks` one read at a time to the general parser, collecting every value
+    fn values(chunks: &[&[u8]], limits: &RespLimits) -> Result<Vec<RespConcreteType>, RespError> {
+        let (mut buf, mut partial) = (BytesMut::new(), None::<RespTypePartialable>);
+        let mut parsed = vec![];
+        for chunk in chunks {
+            buf.extend_from_slice(chunk);
+            while !buf.is_empty() {
+                match parse(&mut buf, partial.take(), limits)? {
+                    Resp::Partial(rest) => {
+                        partial = Some(rest);
+                        break;
+                    }
+                    Resp::Concrete(value) => parsed.push(value),
+                }
+            }
+        }
+        assert!(partial.is_none(), "a value was left unfinished");
+        Ok(parsed)
+    }
+
+    fn value(input: &[u8]) -> Result<RespConcreteType, RespError> {
+        let mut parsed = values(&[input], &RespLimits::default())?;
+        assert_eq!(parsed.len(), 1);
+        Ok(parsed.remove(0))
+    }
+
+    // parses `input` split at every point and encodes what came out, which must be `input` again
+    fn assert_round_trip(input: &[u8], protocol: RespVersion) {
+        let limits = RespLimits::default();
+        let encoded = |parsed: Vec<RespConcreteType>| {
+            let mut out = BytesMut::new();
+            for value in &parsed {
+                encode(value, protocol, &mut out);
+            }
+            out
+        };
+        let whole = values(&[input], &limits).unwrap();
+        assert_eq!(encoded(whole), input);
+        for split in 1..input.len() {
+            let (first, second) = input.split_at(split);
+            let parsed = values(&[first, second], &limits).unwrap();
+            assert_eq!(encoded(parsed), input, "split at {split}");
+        }
+    }
+
+    #[test]
+    fn lines_and_integers() {
+        assert_round_trip(b"+OK\r\n", RespVersion::Resp2);
+        assert_round_trip(b"-ERR unknown command\r\n", RespVersion::Resp2);
+        assert_round_trip(
+            b":0\r\n:-42\r\n:9223372036854775807\r\n",
+            RespVersion::Resp2,
+        );
+        assert_round_trip(b"*3\r\n+a\r\n:1\r\n$2\r\nbc\r\n", RespVersion::Resp2);
+        assert!(matches!(value(b"+\r\n"), Ok(RespConcreteType::SimpleString(s)) if s.is_empty()));
+    }
+
+    #[test]
+    fn crlf_split_across_reads() {
+        let limits = RespLimits::default();
+        // the \r waits in the buffer until its \n arrives
+        let parsed = values(&[b"+OK\r", b"\n:1\r", b"\n"], &limits).unwrap();
+        assert!(matches!(&parsed[..], [
+            RespConcreteType::SimpleString(ok),
+            RespConcreteType::Int(1),
+        ] if ok == "OK"));
+        // a \r on its own is part of the line
+        let parsed = values(&[b"+a\rb\r", b"\n"], &limits).unwrap();
+        assert!(matches!(&parsed[..], [RespConcreteType::SimpleString(s)] if s == "a\rb"));
+    }
+
+    #[test]
+    fn nulls() {
+        assert_round_trip(b"$-1\r\n", RespVersion::Resp2);
+        assert_round_trip(b"*-1\r\n", RespVersion::Resp2);
+        assert!(matches!(
+            value(b"$-1\r\n"),
+            Ok(RespConcreteType::NullBulkString)
+        ));
+        assert!(matches!(value(b"*-1\r\n"), Ok(RespConcreteType::NullArray)));
+        // an empty bulk string is not a null one
+        assert!(
+            matches!(value(b"$0\r\n\r\n"), Ok(RespConcreteType::BulkString(s)) if s.is_empty())
+        );
+    }
+
+    #[test]
+    fn bad_lengths_and_integers() {
+        assert!(matches!(
+            value(b"$-2\r\n"),
+            Err(RespError::BadBulkStringSize(-2))
+        ));
+        assert!(matches!(
+            value(b"*-2\r\n"),
+            Err(RespError::BadArraySize(-2))
+        ));
+        assert!(matches!(
+            value(b"$1\r\nab\r\n"),
+            Err(RespError::BadBulkStringSize(1))
+        ));
+        assert!(matches!(value(b"$x\r\n"), Err(RespError::IntParseFailure)));
+        assert!(matches!(
+            value(b":1.5\r\n"),
+            Err(RespError::IntParseFailure)
+        ));
+        assert!(matches!(
+            value(b":9223372036854775808\r\n"),
+            Err(RespError::IntParseFailure)
+        ));
+
+        // a length that can't fit an i64 is refused before its line ends
+        let limits = RespLimits::default();
+        assert!(matches!(
+            values(&[b"$123456789012345678901"], &limits),
+            Err(RespError::IntParseFailure)
+        ));
+        let limits = RespLimits {
+            max_bulk_len: 5,
+            max_multibulk_len: 5,
+            ..RespLimits::default()
+        };
+        assert!(matches!(
+            values(&[b"$6\r\n"], &limits),
+            Err(RespError::BulkTooLong(6))
+        ));
+        assert!(matches!(
+            values(&[b"*6\r\n"], &limits),
+            Err(RespError::MultibulkTooLong(6))
+        ));
+    }
+
+    #[test]
+    fn resp3_types() {
+        assert_round_trip(b"_\r\n", RespVersion::Resp3);
+        assert_round_trip(b"#t\r\n#f\r\n", RespVersion::Resp3);
+        assert_round_trip(b",1.5\r\n,-0.25\r\n,inf\r\n,-inf\r\n", RespVersion::Resp3);
+        assert_round_trip(
+            b"(-3492890328409238509324850943850943825024385\r\n",
+            RespVersion::Resp3,
+        );
+        assert_round_trip(b"=15\r\ntxt:Some string\r\n", RespVersion::Resp3);
+        assert_round_trip(b"!21\r\nSYNTAX invalid syntax\r\n", RespVersion::Resp3);
+        assert_round_trip(b"%2\r\n+a\r\n:1\r\n$1\r\nb\r\n_\r\n", RespVersion::Resp3);
+        assert_round_trip(b"~2\r\n+a\r\n#f\r\n", RespVersion::Resp3);
+        assert_round_trip(b">2\r\n+message\r\n,2.5\r\n", RespVersion::Resp3);
+        assert_round_trip(b"|1\r\n+ttl\r\n:3600\r\n", RespVersion::Resp3);
+        // aggregates nest inside one another
+        assert_round_trip(
+            b"%1\r\n+set\r\n~1\r\n*2\r\n=7\r\nmkd:**x\r\n(1\r\n",
+            RespVersion::Resp3,
+        );
+
+        assert!(matches!(value(b",nan\r\n"), Ok(RespConcreteType::Double(d)) if d.is_nan()));
+        assert!(matches!(
+            value(b"=7\r\ntxt:abc\r\n"),
+            Ok(RespConcreteType::VerbatimString(v)) if v.format == "txt" && v.string == "abc"
+        ));
+        assert!(matches!(value(b"%-1\r\n"), Ok(RespConcreteType::Null)));
+        assert!(matches!(value(b"=-1\r\n"), Ok(RespConcreteType::Null)));
+    }
+
+    #[test]
+    fn malformed_resp3_types() {
+        assert!(matches!(value(b"_x\r\n"), Err(RespError::NullParseFailure)));
+        assert!(matches!(
+            value(b"#x\r\n"),
+            Err(RespError::BooleanParseFailure)
+        ));
+        assert!(matches!(
+            value(b",1.5x\r\n"),
+            Err(RespError::DoubleParseFailure)
+        ));
+        assert!(matches!(
+            value(b"(12a\r\n"),
+            Err(RespError::BigNumberParseFailure)
+        ));
+        assert!(matches!(
+            value(b"(-\r\n"),
+            Err(RespError::BigNumberParseFailure)
+        ));
+        assert!(matches!(
+            value(b"=3\r\ntxt\r\n"),
+            Err(RespError::VerbatimStringParseFailure)
+        ));
+        assert!(matches!(
+            value(b"=4\r\ntxt-\r\n"),
+            Err(RespError::VerbatimStringParseFailure)
+        ));
+        assert!(matches!(
+            value(b"?\r\n"),
+            Err(RespError::UnknownStartingByte(b'?'))
+        ));
+    }
+
+    #[test]
+    fn resp3_values_read_in_pieces() {
+        let input = b"%2\r\n+key\r\n=9\r\ntxt:a\r\nbc\r\n,3.25\r\n~1\r\n(-17\r\n";
+        let bytes: Vec<&[u8]> = input.chunks(1).collect();
+        let parsed = values(&bytes, &RespLimits::default()).unwrap();
+        let mut out = BytesMut::new();
+        encode(&parsed[0], RespVersion::Resp3, &mut out);
+        assert_eq!(out, &input[..]);
+
+        let RespConcreteType::Map(pairs) = &parsed[0] else {
+            panic!("not a map: {:?}", parsed[0]);
+        };
+        assert!(matches!(
+            &pairs[0],
+            (RespConcreteType::SimpleString(key), RespConcreteType::VerbatimString(v))
+                if key == "key" && v.string == "a\r\nbc"
+        ));
+        assert!(matches!(
+            &pairs[1],
+            (RespConcreteType::Double(d), RespConcreteType::Set(items))
+                if *d == 3.25 && items.len() == 1 && matches!(items.front(), Some(RespConcreteType::BigNumber(n)) if n == "-17")
+        ));
     }
 }
@@ -0,0 +1,45 @@
+use bytes::{Bytes, BytesMut};
+
+use super::{word, RespError, Word};
+
+#[derive(Debug)]
+pub enum RespSimpleString {
+    Concrete(RespSimpleStringConcrete),
+    Partial(RespSimpleStringPartial),
+}
+
+#[derive(Debug)]
+pub struct RespSimpleStringPartial(Bytes);
+
+pub type RespSimpleStringConcrete = String;
+
+impl RespSimpleString {
+    fn from_partial(partial_string: Bytes) -> RespSimpleString {
+        RespSimpleString::Partial(RespSimpleStringPartial(partial_string))
+    }
+}
+
+// simple strings and errors share the same line based framing
+pub fn simple_string(
+    buf: &mut BytesMut,
+    partial: Option<RespSimpleStringPartial>,
+) -> Result<RespSimpleString, RespError> {
+    let RespSimpleStringPartial(partial_bytes) =
+        partial.unwrap_or(RespSimpleStringPartial(Bytes::new()));
+
+    match word(buf) {
+        // if the current word is complete, add to existing partial and return concrete
+        Word::Concrete(word) => {
+            let concatenated_bytes = [partial_bytes.as_ref(), word.as_ref()].concat();
+            let raw =
+                String::from_utf8(concatenated_bytes).map_err(|_| RespError::StringParseFailure)?;
+
+            Ok(RespSimpleString::Concrete(raw))
+        }
+        // return partial
+        Word::Partial(word) => {
+            let concatenated_bytes = Bytes::from([partial_bytes.as_ref(), word.as_ref()].concat());
+            Ok(RespSimpleString::from_partial(concatenated_bytes))
+        }
+    }
+}
@@ -1,32 +1,41 @@
-use bytes::{Bytes, BytesMut};
-use std::{convert::From, str::from_utf8};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 
 use super::{
     int::{int, RespInt},
-    word, RespError, Word,
+    RespError, RespLimits,
 };
 
 #[derive(Debug)]
 pub enum RespString {
     Concrete(RespBulkStringConcrete),
     Partial(RespBulkStringPartial),
+    Null,
 }
 
-pub type RespBulkStringConcrete = String;
+pub type RespBulkStringConcrete = Bytes;
 
 #[derive(Debug)]
 pub struct RespBulkStringPartial {
     length: RespInt,
-    string: Bytes,
+    string: BytesMut,
+    // payload bytes plus the trailing \r\n still to be consumed
+    remaining: usize,
 }
 
+const CRLF: &[u8] = b"\r\n";
+
+// declared lengths are not trusted for preallocation beyond this many bytes
+const MAX_PREALLOCATED_BYTES: usize = 1024 * 1024;
+
 pub fn string_with_partial(
     buf: &mut BytesMut,
     partial: RespBulkStringPartial,
+    limits: &RespLimits,
 ) -> Result<RespString, RespError> {
     let RespBulkStringPartial {
         length,
         string: partial_string,
+        remaining,
     } = partial;
 
     // length at this point could still be partial
@@ -40,52 +49,54 @@ pub fn string_with_partial(
                 return Ok(RespString::Partial(RespBulkStringPartial {
                     length: RespInt::Partial(partial),
                     string: partial_string,
+                    remaining,
                 }))
             }
         },
-        // if concrete length received, then proceed to read string
-        RespInt::Concrete(i) => i,
+        // length was already known, continue reading the payload where we left off
+        RespInt::Concrete(i) => {
+            return string_with_length(buf, partial_string, i as usize, remaining);
+        }
     };
 
-    string_with_length(
-        buf,
-        partial_string,
-        length
-            .try_into()
-            .map_err(|_| RespError::BadBulkStringSize(length))?,
-    )
+    string_with_declared_length(buf, length, limits)
 }
 
-pub fn string_with_length(
+fn string_with_length(
     buf: &mut BytesMut,
-    partial_string: Bytes,
+    mut partial_string: BytesMut,
     length: usize,
+    mut remaining: usize,
 ) -> Result<RespString, RespError> {
-    // length is ready, now read the string
-    match word(buf) {
-        // if the current word is complete, add to existing partial partial and return concrete
-        Word::Concrete(word) => {
-            let concatenated_bytes = Bytes::from([partial_string.as_ref(), word.as_ref()].concat());
-            if concatenated_bytes.len() != length {
-                return Err(RespError::BadBulkStringSize(length as i64));
-            }
-            let raw = from_utf8(&concatenated_bytes).map_err(|_| RespError::StringParseFailure)?;
-            Ok(RespString::Concrete(raw.to_string()))
-        }
-        // return partial string with concrete length but partial string
-        Word::Partial(word) => {
-            let concatenated_bytes = Bytes::from([partial_string.as_ref(), word.as_ref()].concat());
-            Ok(RespString::Partial(RespBulkStringPartial {
-                length: RespInt::Concrete(length as i64),
-                string: concatenated_bytes,
-            }))
+    // the payload is consumed by its declared length, so it may contain any bytes, \r\n included
+    let payload_remaining = remaining.saturating_sub(CRLF.len());
+    let take = payload_remaining.min(buf.remaining());
+    partial_string.put(buf.split_to(take));
+    remaining -= take;
+
+    // once the payload is complete, consume as much of the trailing \r\n as is available
+    if remaining <= CRLF.len() {
+        let expected = &CRLF[CRLF.len() - remaining..];
+        let take = expected.len().min(buf.remaining());
+        if buf[..take] != expected[..take] {
+            return Err(RespError::BadBulkStringSize(length as i64));
         }
+        buf.advance(take);
+        remaining -= take;
     }
-}
 
-pub fn string(buf: &mut BytesMut) -> Result<RespString, RespError> {
-    let partial_string = Bytes::new();
+    match remaining {
+        0 => Ok(RespString::Concrete(partial_string.freeze())),
+        // return partial string with concrete length but partial payload
+        _ => Ok(RespString::Partial(RespBulkStringPartial {
+            length: RespInt::Concrete(length as i64),
+            string: partial_string,
+            remaining,
+        })),
+    }
+}
 
+pub fn string(buf: &mut BytesMut, limits: &RespLimits) -> Result<RespString, RespError> {
     // length at this point could still be partial
     let length = match int(buf, None)? {
         // if concrete length received, then proceed to read string
@@ -94,16 +105,54 @@ pub fn string(buf: &mut BytesMut) -> Result<RespString, RespError> {
         RespInt::Partial(partial) => {
             return Ok(RespString::Partial(RespBulkStringPartial {
                 length: RespInt::Partial(partial),
-                string: partial_string,
+                string: BytesMut::new(),
+                remaining: 0,
             }))
         }
     };
 
-    string_with_length(
-        buf,
-        partial_string,
-        length
-            .try_into()
-            .map_err(|_| RespError::BadBulkStringSize(length))?,
-    )
+    string_with_declared_length(buf, length, limits)
+}
+
+fn string_with_declared_length(
+    buf: &mut BytesMut,
+    length: i64,
+    limits: &RespLimits,
+) -> Result<RespString, RespError> {
+    // $-1 is the RESP2 null bulk string
+    if length == -1 {
+        return Ok(RespString::Null);
+    }
+
+    let length: usize = length
+        .try_into()
+        .map_err(|_| RespError::BadBulkStringSize(length))?;
+    if length > limits.max_bulk_len {
+        return Err(RespError::BulkTooLong(length as i64));
+    }
+
+    let partial_string = BytesMut::with_capacity(length.min(MAX_PREALLOCATED_BYTES));
+    string_with_length(buf, partial_string, length, length + CRLF.len())
+}
+
+#[derive(Debug)]
+pub struct RespVerbatimStringConcrete {
+    pub format: String,
+    pub string: Bytes,
+}
+
+// verbatim strings are bulk strings whose payload starts with a three byte format and a colon
+pub fn verbatim_string(mut payload: Bytes) -> Result<RespVerbatimStringConcrete, RespError> {
+    if payload.len() < 4 || payload[3] != b':' {
+        return Err(RespError::VerbatimStringParseFailure);
+    }
+
+    let format = payload.split_to(3);
+    payload.advance(1);
+
+    Ok(RespVerbatimStringConcrete {
+        format: String::from_utf8(format.to_vec())
+            .map_err(|_| RespError::VerbatimStringParseFailure)?,
+        string: payload,
+    })
 }
@@ -0,0 +1,482 @@
+use bytes::{Bytes, BytesMut};
+use tokio::sync::RwLock;
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::blocking::Blocked;
+use crate::clock::{Clock, SystemClock};
+use crate::dict::Dict;
+
+// the active expiry cycle runs `CYCLE_HZ` times a second, each run may use up to
+// `CYCLE_TIME_PERC` percent of its period, like redis' slow expire cycle
+const CYCLE_HZ: u64 = 10;
+const CYCLE_TIME_PERC: u64 = 25;
+// values with more elements than this are freed on a background thread by UNLINK and FLUSHDB ASYNC
+const LAZYFREE_THRESHOLD: usize = 64;
+// keys sampled per round, and the share of them that has to be expired to go for another round
+const KEYS_PER_LOOP: usize = 20;
+const ACCEPTABLE_STALE_PERC: usize = 25;
+
+/// Everything a key can hold
+#[derive(Debug, Clone)]
+pub enum Value {
+    // mutable, so APPEND, SETRANGE and bit writes change it in place rather than copying it
+    String(BytesMut),
+    List(VecDeque<Bytes>),
+    Hash(Hash),
+    Set(Dict<Bytes, ()>),
+}
+
+impl Value {
+    /// The name TYPE replies with
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+        }
+    }
+
+    // roughly how much work dropping the value is
+    fn free_effort(&self) -> usize {
+        match self {
+            Value::String(_) => 1,
+            Value::List(list) => list.len(),
+            Value::Hash(hash) => hash.len(),
+            Value::Set(set) => set.len(),
+        }
+    }
+
+    pub fn as_string(&mut self) -> Option<&mut BytesMut> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&mut self) -> Option<&mut VecDeque<Bytes>> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_hash(&mut self) -> Option<&mut Hash> {
+        match self {
+            Value::Hash(hash) => Some(hash),
+            _ => None,
+        }
+    }
+
+    pub fn as_set(&mut self) -> Option<&mut Dict<Bytes, ()>> {
+        match self {
+            Value::Set(set) => Some(set),
+            _ => None,
+        }
+    }
+}
+
+/// The fields of a hash, each of which may have a deadline of its own
+#[derive(Debug, Clone, Default)]
+pub struct Hash {
+    fields: Dict<Bytes, Bytes>,
+    // unix milliseconds per field with a ttl, and the same ordered by deadline
+    // so the expired fields are found without looking at all of them
+    expiry_at: HashMap<Bytes, i64>,
+    deadlines: BTreeSet<(i64, Bytes)>,
+}
+
+impl Hash {
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn get(&self, field: &Bytes) -> Option<&Bytes> {
+        self.fields.get(field)
+    }
+
+    /// Sets a field and drops its ttl, like HSET
+    pub fn insert(&mut self, field: Bytes, value: Bytes) -> Option<Bytes> {
+        self.expire(&field, None);
+        self.fields.insert(field, value)
+    }
+
+    /// Sets a field and keeps its ttl, like HINCRBY
+    pub fn update(&mut self, field: Bytes, value: Bytes) -> Option<Bytes> {
+        self.fields.insert(field, value)
+    }
+
+    pub fn remove(&mut self, field: &Bytes) -> Option<Bytes> {
+        self.expire(field, None);
+        self.fields.remove(field)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, &Bytes)> {
+        self.fields.iter()
+    }
+
+    pub fn scan(&self, cursor: u64, visit: impl FnMut(&Bytes, &Bytes)) -> u64 {
+        self.fields.scan(cursor, visit)
+    }
+
+    pub fn random(&self) -> Option<(&Bytes, &Bytes)> {
+        self.fields.random()
+    }
+
+    pub fn expiry_at(&self, field: &Bytes) -> Option<i64> {
+        self.expiry_at.get(field).copied()
+    }
+
+    /// Sets the deadline of a field, `None` makes it persistent. Returns the previous deadline
+    pub fn expire(&mut self, field: &Bytes, expiry_at: Option<i64>) -> Option<i64> {
+        let previous = self.expiry_at.remove(field);
+        if let Some(previous) = previous {
+            self.deadlines.remove(&(previous, field.clone()));
+        }
+        if let Some(expiry_at) = expiry_at {
+            self.expiry_at.insert(field.clone(), expiry_at);
+            self.deadlines.insert((expiry_at, field.clone()));
+        }
+        previous
+    }
+
+    /// Whether any field has a ttl
+    pub fn is_volatile(&self) -> bool {
+        !self.deadlines.is_empty()
+    }
+
+    /// Deletes the fields whose deadline passed, returning how many there were
+    fn purge(&mut self, now: i64) -> usize {
+        let mut purged = 0;
+        while let Some((expiry_at, _)) = self.deadlines.first() {
+            if now <= *expiry_at {
+                break;
+            }
+            let (_, field) = self.deadlines.pop_first().expect("just looked at it");
+            self.expiry_at.remove(&field);
+            self.fields.remove(&field);
+            purged += 1;
+        }
+        purged
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageValue {
+    pub value: Value,
+    // unix milliseconds as read from the db's clock,
+    // private so the ttl index of the db can't go out of sync, see `Db::expire`
+    expiry_at: Option<i64>,
+}
+
+impl StorageValue {
+    pub fn new(value: Value, expiry_at: Option<i64>) -> Self {
+        StorageValue { value, expiry_at }
+    }
+
+    pub fn expiry_at(&self) -> Option<i64> {
+        self.expiry_at
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expiry_at.is_some_and(|expiry_at| now > expiry_at)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ExpireStats {
+    /// keys deleted because their ttl ran out, lazily or by the active cycle
+    pub expired_keys: u64,
+    /// hash fields deleted because their own ttl ran out
+    pub expired_subkeys: u64,
+    /// running estimate of the share of keys with a ttl that are already expired
+    pub expired_stale_perc: f64,
+    /// active cycles that stopped because they ran out of time
+    pub expired_time_cap_reached_count: u64,
+    pub expire_cycle_cpu_milliseconds: u64,
+}
+
+#[derive(Debug)]
+pub struct Db {
+    entries: Dict<Bytes, StorageValue>,
+    // the keys with a ttl, sampled by the active expiry cycle
+    volatile: Dict<Bytes, ()>,
+    // the hashes with fields that have a ttl, sampled by the active expiry cycle as well
+    volatile_hashes: Dict<Bytes, ()>,
+    clock: Arc<dyn Clock>,
+    pub stats: ExpireStats,
+    // clients in BLPOP and friends, kept under the same lock as the keys they wait on
+    pub blocked: Blocked,
+}
+
+impl Default for Db {
+    fn default() -> Self {
+        Db::new(Arc::new(SystemClock::new()))
+    }
+}
+
+impl Db {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Db {
+            entries: Dict::default(),
+            volatile: Dict::default(),
+            volatile_hashes: Dict::default(),
+            clock,
+            stats: ExpireStats::default(),
+            blocked: Blocked::default(),
+        }
+    }
+
+    /// Current time in unix milliseconds, the timeline every deadline is on
+    pub fn now(&self) -> i64 {
+        self.clock.now_ms()
+    }
+
+    /// Looks up a key, lazily deleting it first when it has expired
+    pub fn live(&mut self, key: &Bytes) -> Option<&mut StorageValue> {
+        let now = self.now();
+        if self
+            .entries
+            .get(key)
+            .is_some_and(|value| value.is_expired(now))
+        {
+            self.remove(key);
+            self.stats.expired_keys += 1;
+        }
+
+        // the expired fields of a hash go first, and the hash with its last one
+        if let Some(StorageValue {
+            value: Value::Hash(hash),
+            ..
+        }) = self.entries.get_mut(key)
+        {
+            self.stats.expired_subkeys += hash.purge(now) as u64;
+            if hash.is_empty() {
+                self.remove(key);
+            }
+        }
+        self.entries.get_mut(key)
+    }
+
+    /// Looks up a key as it is, without expiring it. For reading several keys at
+    /// once after `live` went over each of them
+    pub fn get(&self, key: &Bytes) -> Option<&StorageValue> {
+        self.entries.get(key)
+    }
+
+    /// Looks up a key like `live`, creating it without a ttl when missing
+    pub fn get_or_insert(
+        &mut self,
+        key: &Bytes,
+        value: impl FnOnce() -> Value,
+    ) -> &mut StorageValue {
+        self.live(key);
+        self.entries
+            .get_or_insert_with(key.clone(), || StorageValue::new(value(), None))
+    }
+
+    pub fn insert(&mut self, key: Bytes, value: StorageValue) -> Option<StorageValue> {
+        match value.expiry_at {
+            Some(_) => self.volatile.insert(key.clone(), ()),
+            None => self.volatile.remove(&key),
+        };
+        match &value.value {
+            Value::Hash(hash) if hash.is_volatile() => self.volatile_hashes.insert(key.clone(), ()),
+            _ => self.volatile_hashes.remove(&key),
+        };
+        self.entries.insert(key, value)
+    }
+
+    pub fn remove(&mut self, key: &Bytes) -> Option<StorageValue> {
+        self.volatile.remove(key);
+        self.volatile_hashes.remove(key);
+        self.entries.remove(key)
+    }
+
+    /// Sets the deadline of an existing key, `None` makes it persistent.
+    /// Returns the previous deadline, or `None` when the key doesn't exist
+    pub fn expire(&mut self, key: &Bytes, expiry_at: Option<i64>) -> Option<Option<i64>> {
+        let value = self.entries.get_mut(key)?;
+        match expiry_at {
+            Some(_) => self.volatile.insert(key.clone(), ()),
+            None => self.volatile.remove(key),
+        };
+        Some(std::mem::replace(&mut value.expiry_at, expiry_at))
+    }
+
+    /// Lets the active expiry cycle know the hash at `key` has fields with a ttl now
+    pub fn track_field_expiry(&mut self, key: &Bytes) {
+        if self.entries.get(key).is_some() {
+            self.volatile_hashes.insert(key.clone(), ());
+        }
+    }
+
+    /// Removes a key like `remove`, but frees a large value off the calling thread
+    pub fn unlink(&mut self, key: &Bytes) -> bool {
+        match self.remove(key) {
+            Some(value) if value.value.free_effort() > LAZYFREE_THRESHOLD => {
+                std::thread::spawn(move || drop(value));
+                true
+            }
+            removed => removed.is_some(),
+        }
+    }
+
+    /// Deletes every key, freeing them on a background thread when `lazy`
+    pub fn flush(&mut self, lazy: bool) {
+        let entries = std::mem::take(&mut self.entries);
+        self.volatile = Dict::default();
+        self.volatile_hashes = Dict::default();
+        if lazy {
+            std::thread::spawn(move || drop(entries));
+        }
+    }
+
+    /// A random live key, expired keys met on the way are deleted
+    pub fn random_key(&mut self) -> Option<Bytes> {
+        // when every key has a ttl they could all be expired, so give up after a while
+        for _ in 0..100 {
+            if self.entries.is_empty() {
+                return None;
+            }
+            let (key, _) = self.entries.random()?;
+            let key = key.clone();
+            if self.live(&key).is_some() {
+                return Some(key);
+            }
+        }
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Number of keys with a ttl
+    pub fn expires(&self) -> usize {
+        self.volatile.len()
+    }
+
+    /// Every key, expired or not
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, &StorageValue)> {
+        self.entries.iter()
+    }
+
+    /// One step of a SCAN, see `Dict::scan`
+    pub fn scan(&self, cursor: u64, visit: impl FnMut(&Bytes, &StorageValue)) -> u64 {
+        self.entries.scan(cursor, visit)
+    }
+
+    // samples random keys with a ttl and deletes the expired ones, going for another round
+    // while more than `ACCEPTABLE_STALE_PERC` of a sample was expired and time is left
+    fn active_expire_cycle(&mut self, budget: Duration) {
+        let start = Instant::now();
+        let (mut sampled, mut expired) = (0, 0);
+
+        while !self.volatile.is_empty() {
+            let round = KEYS_PER_LOOP.min(self.volatile.len());
+            let mut round_expired = 0;
+            let now = self.now();
+            for _ in 0..round {
+                let Some((key, _)) = self.volatile.random() else {
+                    break;
+                };
+                let key = key.clone();
+                if self
+                    .entries
+                    .get(&key)
+                    .is_some_and(|value| value.is_expired(now))
+                {
+                    self.remove(&key);
+                    round_expired += 1;
+                }
+            }
+            sampled += round;
+            expired += round_expired;
+
+            if round_expired * 100 <= round * ACCEPTABLE_STALE_PERC {
+                break;
+            }
+            if start.elapsed() > budget {
+                self.stats.expired_time_cap_reached_count += 1;
+                break;
+            }
+        }
+
+        self.stats.expired_keys += expired as u64;
+        self.active_expire_fields(start, budget);
+        self.stats.expire_cycle_cpu_milliseconds += start.elapsed().as_millis() as u64;
+        // smoothed over cycles, an empty sample says nothing about staleness
+        if sampled > 0 {
+            let current = expired as f64 / sampled as f64;
+            self.stats.expired_stale_perc = current * 0.05 + self.stats.expired_stale_perc * 0.95;
+        }
+    }
+
+    // the same for hashes with expiring fields, in what is left of the cycle's time.
+    // A sampled hash has all its expired fields deleted, and is stale if it had any
+    fn active_expire_fields(&mut self, start: Instant, budget: Duration) {
+        // a cycle that ran out of time on keys was counted already
+        if start.elapsed() > budget {
+            return;
+        }
+        while !self.volatile_hashes.is_empty() {
+            let round = KEYS_PER_LOOP.min(self.volatile_hashes.len());
+            let mut round_stale = 0;
+            let now = self.now();
+            for _ in 0..round {
+                let Some((key, _)) = self.volatile_hashes.random() else {
+                    break;
+                };
+                let key = key.clone();
+                let Some(StorageValue {
+                    value: Value::Hash(hash),
+                    ..
+                }) = self.entries.get_mut(&key)
+                else {
+                    self.volatile_hashes.remove(&key);
+                    continue;
+                };
+
+                let purged = hash.purge(now);
+                let (emptied, volatile) = (hash.is_empty(), hash.is_volatile());
+                self.stats.expired_subkeys += purged as u64;
+                round_stale += (purged > 0) as usize;
+                if emptied {
+                    self.remove(&key);
+                } else if !volatile {
+                    self.volatile_hashes.remove(&key);
+                }
+            }
+
+            if round_stale * 100 <= round * ACCEPTABLE_STALE_PERC {
+                break;
+            }
+            if start.elapsed() > budget {
+                self.stats.expired_time_cap_reached_count += 1;
+                break;
+            }
+        }
+    }
+}
+
+pub type Storage = Arc<RwLock<Db>>;
+
+/// Runs the active expiry cycle forever, deleting keys that expired without being read again
+pub async fn active_expire(storage: Storage) {
+    let period = Duration::from_millis(1000 / CYCLE_HZ);
+    let budget = period * CYCLE_TIME_PERC as u32 / 100;
+
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        storage.write().await.active_expire_cycle(budget);
+    }
+}